@@ -1,13 +1,64 @@
 // src/ast.rs
+use std::rc::Rc;
+
+use crate::diagnostics::Spanned;
 use crate::lexer::Token;
 
+// The variable holding the result of the most recent command. Commands whose
+// source or `as` destination is left out read from and write to it. It is the
+// only variable a command sets without being asked to; `result` reads it too.
+pub const IMPLICIT_RESULT: &str = "it";
+
 // ... Expression, ArithmeticOp, FilterCondition enums are the same ...
 #[derive(Debug, PartialEq, Clone)]
-pub enum Expression { Identifier(String), StringLiteral(String), Number(i64), Unit(Token) }
-#[derive(Debug, PartialEq)]
+pub enum Expression {
+    Identifier(String), StringLiteral(String), Number(i64), Float(f64), Boolean(bool), Unit(Token),
+    // Operands keep their spans so an error can point at the one that caused it.
+    Arithmetic { op: ArithmeticOp, left: Box<Spanned<Expression>>, right: Box<Spanned<Expression>> }, // `types / tokens`
+    Comparison { op: ComparisonOp, left: Box<Spanned<Expression>>, right: Box<Spanned<Expression>> }, // `n is greater than 100`
+    Logical { op: LogicalOp, left: Box<Expression>, right: Box<Expression> },       // `a and b`
+    Contains { haystack: Box<Expression>, needle: Box<Expression> },                // `name contains "Reed"`
+    Not(Box<Expression>),
+    Negate(Box<Expression>),
+    List(Vec<Expression>),                                                           // `["a", "b"]`
+    Tuple(Vec<Expression>),                                                          // `("Reed", "PERSON")`
+    Map(Vec<(String, Expression)>),                                                  // `{ name: "Reed", label: "PERSON" }`
+    Item { index: Box<Expression>, collection: Box<Expression> },                    // `item 3 of words` (counting from 1)
+    // `first 10 of words`, `last 3 of words`; without a count (`first of words`)
+    // it is the single first or last item.
+    Slice { end: SliceEnd, count: Option<Box<Expression>>, collection: Box<Expression> },
+    Field { name: String, record: Box<Expression> },                                 // `the label of entity`
+    Keys(Box<Expression>),                                                           // `keys of record`
+    Values(Box<Expression>),                                                         // `values of record`
+    ToTable(Box<Expression>),                                                        // `table of people`
+    // `top 20 of freq`, `bottom 5 of freq`: the rows with the highest (lowest) count.
+    Top { from_bottom: bool, count: Box<Expression>, collection: Box<Expression> },
+}
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SliceEnd { First, Last }
+// One column to sort a table by. Later keys only decide between rows that
+// are equal on the earlier ones.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SortKey { pub column: String, pub descending: bool }
+// Which side of a concordance line to sort by, reading outwards from the keyword.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ContextSide { Left, Right }
+// One step of `clean`; most are also commands of their own (`lowercase`,
+// `remove punctuation from ...`).
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CleaningStep { Normalize, Lowercase, Punctuation, Numbers, Stopwords, Whitespace }
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum ArithmeticOp { Add, Subtract, Multiply, Divide }
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ComparisonOp { Equal, NotEqual, Less, LessEqual, Greater, GreaterEqual }
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LogicalOp { And, Or }
 #[derive(Debug, PartialEq)]
-pub enum FilterCondition { Containing(Expression), StartingWith(Expression), EndingWith(Expression) }
+pub enum FilterCondition {
+    Containing(Expression), StartingWith(Expression), EndingWith(Expression),
+    Where(Expression), // `filter freq where count > 10`: the condition can use the row's columns as names
+    Matching(Expression), // `filter words matching regex "^un"`: a regular expression found anywhere in the item
+}
 
 // New enum to represent the language backends
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
@@ -17,9 +68,11 @@ pub enum Backend { Python, R, Java }
 pub enum Statement {
     Use(Backend), // New: `use python`
     Load { source: Expression, alias: Expression },
+    LoadStopwords { path: Expression, destination: Expression }, // `load stopwords from "my_list.txt"`
     Save { source: Expression, destination: Expression },
     Print(Expression),
-    Define { name: Expression, value: Expression },
+    Define { name: Expression, value: Expression },  // Creates `name` in the current scope
+    Assign { name: Expression, value: Expression },  // `set name to ...`: changes the nearest existing `name`
     Tokenize { source: Expression, destination: Expression },
     Count { unit: Expression, source: Expression, destination: Expression },
     Tag { source: Expression, method: Expression, destination: Expression },
@@ -27,4 +80,42 @@ pub enum Statement {
     Lemmatize { source: Expression, destination: Expression },
     Filter { source: Expression, condition: FilterCondition, destination: Expression },
     Summarize { source: Expression, destination: Expression }, // New
+    SetPrecision(Expression), // `set precision to 2`: decimals shown for floats
+    If { condition: Expression, then_branch: Vec<Spanned<Statement>>, else_branch: Vec<Spanned<Statement>> },
+    ForEach { variable: Expression, iterable: Expression, body: Vec<Spanned<Statement>> }, // `for each x in xs ... end`
+    Repeat { count: Expression, body: Vec<Spanned<Statement>> },                           // `repeat 3 times ... end`
+    // `define procedure clean_text taking text ... end`. The body is shared with
+    // the procedure value created when the definition runs.
+    DefineProcedure { name: Expression, parameters: Vec<String>, body: Rc<Vec<Spanned<Statement>>> },
+    Call { name: Expression, arguments: Vec<Expression>, destination: Option<Expression> }, // `clean_text with my_article as cleaned`
+    Return(Option<Expression>),
+    // `load "a.txt" then tokenize then count words`: each stage after the first
+    // works on the result of the one before it.
+    Pipeline(Vec<Spanned<Statement>>),
+    Select { columns: Vec<String>, source: Expression, destination: Expression },          // `select text, label from entities as t`
+    // `sort words descending`, `sort freq by count descending, word`
+    Sort { source: Expression, keys: Vec<SortKey>, descending: bool, destination: Expression },
+    // `frequency of words in tokens [ignoring case] as freq`
+    Frequency { unit: Expression, source: Expression, ignore_case: bool, destination: Expression },
+    Rank { source: Expression, key: String, ascending: bool, destination: Expression },   // `rank scores by score`
+    // `concordance "love" in novel width 40 sorted by left as lines` (or `kwic`)
+    Concordance { keyword: Expression, source: Expression, width: Option<Expression>, sorted_by: Option<ContextSide>, destination: Expression },
+    // `replace "ſ" with "s" in text as modern`; `replace regex "\\s+" with " " in text`
+    Replace { pattern: Expression, replacement: Expression, literal: bool, source: Expression, ignore_case: bool, destination: Expression },
+    ChangeCase { source: Expression, upper: bool, destination: Expression },              // `lowercase text as lower`
+    // `remove punctuation from tokens`, `remove stopwords in latin from tokens`. In every
+    // statement with one, `stopword_list` names a language or a list variable; without
+    // it the project's list is used (English unless `load stopwords` replaced it).
+    Remove { what: CleaningStep, stopword_list: Option<String>, source: Expression, destination: Expression },
+    // `normalize text [to nfkc]`: NFC (or NFKC), plain quotes and dashes, single spaces
+    Normalize { source: Expression, compatibility: bool, destination: Expression },
+    // `clean text with lowercase, punctuation`; without `with`, the default steps
+    Clean { source: Expression, steps: Vec<CleaningStep>, stopword_list: Option<String>, destination: Expression },
+    // `extract bigrams from tokens [skipping stopwords] [with counts] as pairs`; `ngrams of size 4`
+    Ngrams { size: Expression, source: Expression, skip_stopwords: bool, stopword_list: Option<String>, counted: bool, destination: Expression },
+    // `find pattern "colou?r" in text [ignoring case] as hits`; `find "colour"` searches for the text as written
+    Find { pattern: Expression, literal: bool, source: Expression, ignore_case: bool, destination: Expression },
+    // `collocate "freedom" in corpus window 5 by mi as colls`
+    Collocate { node: Expression, source: Expression, window: Option<Expression>, ranked_by: Option<String>, destination: Expression },
+    Exit,
 }
\ No newline at end of file
@@ -0,0 +1,143 @@
+// src/diagnostics.rs
+
+// Source positions and human-readable error reports.
+// Every token and statement carries a `Span`, so any error can point back at
+// the exact place in the script that caused it.
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,  // Byte offset of the first character
+    pub end: usize,    // Byte offset just past the last character
+    pub line: usize,   // 1-based line of `start`
+    pub column: usize, // 1-based column of `start`, counted in characters
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+        Span { start, end, line, column }
+    }
+
+    // A span covering both `self` and `other` (e.g. a whole statement).
+    pub fn to(&self, other: Span) -> Span {
+        Span { start: self.start, end: other.end.max(self.end), line: self.line, column: self.column }
+    }
+}
+
+// A value paired with the place in the source it came from.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Span) -> Self {
+        Spanned { node, span }
+    }
+}
+
+// Only this many procedure calls are listed under a runtime error; deeper
+// ones (e.g. from runaway recursion) are summarized in one line.
+const MAX_TRACE_FRAMES: usize = 5;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Span,
+    pub hint: Option<String>,
+    // The procedure calls an error happened in, innermost first, as
+    // (procedure name, line of the call).
+    pub trace: Vec<(String, usize)>,
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>, span: Span) -> Self {
+        Diagnostic { message: message.into(), span, hint: None, trace: Vec::new() }
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    // Records that the error happened inside a call to `procedure` made at `call`.
+    pub fn called_from(mut self, procedure: &str, call: Span) -> Self {
+        self.trace.push((procedure.to_string(), call.line));
+        self
+    }
+
+    // Renders the error with the offending line and a caret under the problem:
+    //
+    //   Parse Error: Expected `as` here, but found the name `x`.
+    //    --> line 1, column 15
+    //     |
+    //   1 | load "a.txt" x
+    //     |              ^
+    //     = hint: did you mean `as`?
+    //
+    // Runtime errors inside procedures point at the failing statement in the
+    // procedure's body and then list the calls that led there:
+    //
+    //     = in procedure 'average', called on line 12
+    pub fn render(&self, kind: &str, source: &str) -> String {
+        let mut out = format!("{}: {}\n", kind, self.message);
+        let line_text = source.lines().nth(self.span.line.saturating_sub(1));
+        out.push_str(&format!(" --> line {}, column {}\n", self.span.line, self.span.column));
+
+        if let Some(line_text) = line_text {
+            let gutter = self.span.line.to_string().len();
+            let pad = " ".repeat(gutter);
+            // The caret never runs past the end of the line it starts on.
+            let line_len = line_text.chars().count();
+            let caret_start = self.span.column.saturating_sub(1).min(line_len);
+            let width = source.get(self.span.start..self.span.end)
+                .map_or(1, |text| text.chars().take_while(|&c| c != '\n').count())
+                .clamp(1, (line_len - caret_start).max(1));
+            out.push_str(&format!("{} |\n", pad));
+            out.push_str(&format!("{} | {}\n", self.span.line, line_text));
+            out.push_str(&format!("{} | {}{}\n", pad, " ".repeat(caret_start), "^".repeat(width)));
+            if let Some(hint) = &self.hint {
+                out.push_str(&format!("{} = hint: {}\n", pad, hint));
+            }
+        } else if let Some(hint) = &self.hint {
+            out.push_str(&format!("  = hint: {}\n", hint));
+        }
+        let pad = " ".repeat(self.span.line.to_string().len());
+        for (procedure, line) in self.trace.iter().take(MAX_TRACE_FRAMES) {
+            out.push_str(&format!("{} = in procedure '{}', called on line {}\n", pad, procedure, line));
+        }
+        if self.trace.len() > MAX_TRACE_FRAMES {
+            out.push_str(&format!("{} = ... and {} more calls\n", pad, self.trace.len() - MAX_TRACE_FRAMES));
+        }
+        out
+    }
+}
+
+// Suggests the closest candidate to a misspelled word, if one is close enough
+// to be a plausible typo.
+pub fn suggest<'a>(word: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let word = word.to_lowercase();
+    // One- and two-letter names are too short for a meaningful guess.
+    if word.chars().count() < 3 { return None; }
+    let max_distance = if word.chars().count() <= 3 { 1 } else { 2 };
+    candidates.into_iter()
+        .map(|c| (edit_distance(&word, &c.to_lowercase()), c))
+        .filter(|(d, c)| *d > 0 && *d <= max_distance && !c.is_empty())
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            current[j + 1] = (previous[j] + cost).min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
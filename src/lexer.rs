@@ -1,5 +1,7 @@
 // src/lexer.rs
 
+use crate::diagnostics::{Diagnostic, Span, Spanned};
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
     // === Verbs (Commands) ===
@@ -13,10 +15,12 @@ pub enum Token {
     Count, Tally, Measure, Calculate, Get, Rank,
     Find, Search, Locate, Extract, Match, Filter, Keep, Remove, Exclude, Slice,
     Tag, Annotate, Concordance, Collocate, Frequency, Cluster, Correlate, Compare, Summarize,
-    Sort, Order, Group,
+    Sort, Order, Group, Select,
     Add, Subtract, Multiply, Divide,
     Help, Docs, History, Run, Execute, Exit, Quit,
     Use, Python, R, Java, Scala, Ruby,Julia,
+    If, Otherwise, End, For, Each, Repeat,
+    Procedure, Taking, Return, Then,
 
     // === Nouns (Units, Targets, Concepts) ===
     Words, Sentences, Lines, Paragraphs, Characters, Tokens, Types, Uniques,
@@ -26,12 +30,12 @@ pub enum Token {
     Entities, POS, NER,
     Bigrams, Trigrams, Ngrams,
     URL, JSON, CSV, XML,
-    First, Last, Sample,
+    First, Last, Item, Sample,
     Distribution, KWIC,
 
     // === Keywords & Prepositions ===
-    As, To, From, In, Into, On, By, With,
-    Containing, StartingWith, EndingWith,
+    As, To, From, In, Into, On, By, With, Of,
+    Containing, StartingWith, EndingWith, Where,
     Ascending, Descending,
     Top, Bottom,
 
@@ -39,55 +43,165 @@ pub enum Token {
     Identifier(String),
     String(String),
     Number(i64),
+    Float(f64),
+
+    // === Operators ===
+    Plus, Minus, Times, Divided,              // + - * / (and `plus`, `minus`, `times`, `divided by`)
+    Equal, NotEqual,                          // = == != (and `equals`, `equal to`)
+    Less, LessEqual, Greater, GreaterEqual,   // < <= > >= (and `less than`, `greater than`)
+    And, Or, Not, Is, Than, Contains,
+    True, False,
+    LeftParen, RightParen, Comma,
+
+    // === Punctuation ===
+    LeftBracket, RightBracket, // [ ] around a list
+    LeftBrace, RightBrace,     // { } around a record
+    Colon,                     // between a record's key and its value
+    Newline,    // A line break ends a statement...
+    Terminator, // ...and so does an explicit `.` or `;`
 
     // === Other ===
     Eof,
     Illegal(String),
 }
 
+impl Token {
+    // How the token is written in a script, e.g. `Token::As` => "as".
+    pub fn spelling(&self) -> String {
+        match self {
+            Token::Identifier(name) => name.clone(),
+            Token::String(s) => format!("\"{}\"", s),
+            Token::Number(n) => n.to_string(),
+            Token::Float(f) => f.to_string(),
+            Token::StartingWith => "starting_with".to_string(),
+            Token::LeftParen => "(".to_string(),
+            Token::RightParen => ")".to_string(),
+            Token::Comma => ",".to_string(),
+            Token::LeftBracket => "[".to_string(),
+            Token::RightBracket => "]".to_string(),
+            Token::LeftBrace => "{".to_string(),
+            Token::RightBrace => "}".to_string(),
+            Token::Colon => ":".to_string(),
+            Token::NotEqual => "!=".to_string(),
+            Token::LessEqual => "<=".to_string(),
+            Token::GreaterEqual => ">=".to_string(),
+            Token::EndingWith => "ending_with".to_string(),
+            Token::Eof => String::new(),
+            Token::Illegal(text) => text.clone(),
+            other => format!("{:?}", other).to_lowercase(),
+        }
+    }
+
+    // A plain-English description for error messages.
+    pub fn describe(&self) -> String {
+        match self {
+            Token::Identifier(name) => format!("the name `{}`", name),
+            Token::String(s) => format!("the text \"{}\"", s),
+            Token::Number(n) => format!("the number {}", n),
+            Token::Float(f) => format!("the number {}", f),
+            Token::Eof => "the end of the script".to_string(),
+            Token::Newline => "the end of the line".to_string(),
+            Token::Terminator => "the end of the statement (`.` or `;`)".to_string(),
+            Token::Illegal(text) => format!("the character `{}`", text),
+            other => format!("the word `{}`", other.spelling()),
+        }
+    }
+}
+
+// Scans over the input's `char_indices` once up front, so looking at the
+// current character is O(1) and every span is an exact byte range into the
+// source, even for accented, Greek, Devanagari or CJK text.
 pub struct Lexer<'a> {
     input: &'a str,
-    position: usize,
+    chars: Vec<(usize, char)>, // (byte offset, character)
+    index: usize,              // Index into `chars`, not a byte offset
+    line: usize,
+    column: usize,
+    errors: Vec<Diagnostic>,
+    bracket_depth: usize, // Line breaks inside (), [] or {} don't end the statement
+    cut_short: bool,      // The input ended inside a triple-quoted string or after a `\`
 }
 
 impl<'a> Lexer<'a> {
-    pub fn new(input: &'a str) -> Self { Lexer { input, position: 0 } }
+    pub fn new(input: &'a str) -> Self {
+        Lexer { input, chars: input.char_indices().collect(), index: 0, line: 1, column: 1, errors: Vec::new(), bracket_depth: 0, cut_short: false }
+    }
 
-    pub fn all_tokens(&mut self) -> Vec<Token> {
+    pub fn all_tokens(&mut self) -> Vec<Spanned<Token>> {
         let mut tokens = Vec::new();
         loop {
             let token = self.next_token();
-            let is_eof = matches!(token, Token::Eof);
+            let is_eof = matches!(token.node, Token::Eof);
             tokens.push(token);
             if is_eof { break; }
         }
         tokens
     }
 
-    fn next_token(&mut self) -> Token {
+    // Problems found while scanning (e.g. characters Talea doesn't understand).
+    pub fn errors(&self) -> &[Diagnostic] { &self.errors }
+
+    // True if the input stopped in the middle of a statement: inside brackets, a
+    // triple-quoted string or after a line continuation. The REPL then waits for
+    // more lines instead of reporting errors.
+    pub fn is_unfinished(&self) -> bool { self.bracket_depth > 0 || self.cut_short }
+
+    fn next_token(&mut self) -> Spanned<Token> {
         self.skip_whitespace();
-        if self.position >= self.input.len() { return Token::Eof; }
-        let ch = self.current_char().unwrap();
+        let start = self.here();
+        let token = self.scan_token();
+        Spanned::new(token, self.span_from(start))
+    }
+
+    fn scan_token(&mut self) -> Token {
+        let Some(ch) = self.current_char() else { return Token::Eof; };
         match ch {
-            '"' => self.read_string(),
+            '\n' => { self.advance(); Token::Newline }
+            '.' | ';' => { self.advance(); Token::Terminator }
+            '+' => { self.advance(); Token::Plus }
+            '-' => { self.advance(); Token::Minus }
+            '*' => { self.advance(); Token::Times }
+            '/' => { self.advance(); Token::Divided }
+            '(' => { self.open_bracket(); Token::LeftParen }
+            ')' => { self.close_bracket(); Token::RightParen }
+            '[' => { self.open_bracket(); Token::LeftBracket }
+            ']' => { self.close_bracket(); Token::RightBracket }
+            '{' => { self.open_bracket(); Token::LeftBrace }
+            '}' => { self.close_bracket(); Token::RightBrace }
+            ':' => { self.advance(); Token::Colon }
+            ',' => { self.advance(); Token::Comma }
+            '=' => { self.advance(); if self.current_char() == Some('=') { self.advance(); } Token::Equal }
+            '!' if self.peek_char() == Some('=') => { self.advance(); self.advance(); Token::NotEqual }
+            '<' => { self.advance(); if self.current_char() == Some('=') { self.advance(); Token::LessEqual } else { Token::Less } }
+            '>' => { self.advance(); if self.current_char() == Some('=') { self.advance(); Token::GreaterEqual } else { Token::Greater } }
+            '"' | '\'' => self.read_string(ch),
             _ if ch.is_alphabetic() => self.read_identifier(),
-            _ if ch.is_digit(10) => self.read_number(),
-            _ => { self.advance(); Token::Illegal(ch.to_string()) }
+            _ if ch.is_ascii_digit() => self.read_number(),
+            _ => {
+                let start = self.here();
+                self.advance();
+                self.errors.push(Diagnostic::new(format!("Talea doesn't understand the character `{}`.", ch), self.span_from(start))
+                    .with_hint("remove it, or put it inside quotes if it is part of some text"));
+                Token::Illegal(ch.to_string())
+            }
         }
     }
 
+    fn open_bracket(&mut self) { self.advance(); self.bracket_depth += 1; }
+    fn close_bracket(&mut self) { self.advance(); self.bracket_depth = self.bracket_depth.saturating_sub(1); }
+
     fn read_identifier(&mut self) -> Token {
-        let start = self.position;
+        let start = self.offset();
         while let Some(ch) = self.current_char() {
-            if !ch.is_alphanumeric() && ch != '_' { break; }
+            if !is_identifier_char(ch) { break; }
             self.advance();
         }
-        let text = &self.input[start..self.position];
+        let text = &self.input[start..self.offset()];
         match text.to_lowercase().as_str() {
             "load"|"read"|"open" => Token::Load, "fetch"|"download" => Token::Fetch, "connect" => Token::Connect,
             "save"|"write"|"export" => Token::Save, "print"|"show"|"display"|"view" => Token::Print,
             "inspect"|"preview" => Token::Inspect, "head" => Token::Head, "tail" => Token::Tail,
-            "define"|"let"|"create"|"set"|"assign" => Token::Define, "clear"|"reset" => Token::Reset,
+            "define"|"let"|"create" => Token::Define, "set" => Token::Set, "assign" => Token::Assign, "clear"|"reset" => Token::Reset,
             "tokenize"|"split"|"segment" => Token::Tokenize, "join"|"merge"|"concatenate" => Token::Join,
             "replace"|"substitute" => Token::Replace, "clean" => Token::Clean, "normalize" => Token::Normalize,
             "stem" => Token::Stem, "lemmatize" => Token::Lemmatize, "lowercase" => Token::Lowercase,
@@ -97,7 +211,7 @@ impl<'a> Lexer<'a> {
             "remove"|"exclude" => Token::Remove, "slice" => Token::Slice, "tag"|"annotate" => Token::Tag,
             "concordance" => Token::Concordance, "collocate" => Token::Collocate, "frequency" => Token::Frequency,
             "cluster" => Token::Cluster, "correlate" => Token::Correlate, "compare" => Token::Compare,
-            "summarize" => Token::Summarize, "sort"|"order" => Token::Sort, "group" => Token::Group,
+            "summarize" => Token::Summarize, "sort"|"order" => Token::Sort, "select" => Token::Select, "group" => Token::Group,
             "add" => Token::Add, "subtract" => Token::Subtract, "multiply" => Token::Multiply, "divide" => Token::Divide,
             "help"|"docs" => Token::Help, "history" => Token::History, "run"|"execute" => Token::Run,
             "exit"|"quit" => Token::Exit, "words" => Token::Words, "sentences" => Token::Sentences,
@@ -108,21 +222,188 @@ impl<'a> Lexer<'a> {
             "pattern" => Token::Pattern, "regex" => Token::Regex, "entities" => Token::Entities,
             "pos" => Token::POS, "ner" => Token::NER, "bigrams" => Token::Bigrams, "trigrams" => Token::Trigrams,
             "ngrams" => Token::Ngrams, "url" => Token::URL, "json" => Token::JSON, "csv" => Token::CSV,
-            "xml" => Token::XML, "first" => Token::First, "last" => Token::Last, "sample" => Token::Sample,
+            "xml" => Token::XML, "first" => Token::First, "last" => Token::Last, "item" => Token::Item, "of" => Token::Of, "sample" => Token::Sample,
             "distribution" => Token::Distribution, "kwic" => Token::KWIC, "as" => Token::As, "to" => Token::To,
             "from" => Token::From, "in" => Token::In, "into" => Token::Into, "on" => Token::On, "by" => Token::By,
             "with" => Token::With, "containing" => Token::Containing, "starting_with" => Token::StartingWith,
-            "ending_with" => Token::EndingWith, "ascending" => Token::Ascending, "descending" => Token::Descending,
+            "ending_with" => Token::EndingWith, "where" => Token::Where, "ascending" => Token::Ascending, "descending" => Token::Descending,
             "top" => Token::Top, "bottom" => Token::Bottom,
             "use" => Token::Use, "python" => Token::Python, "r" => Token::R,  // New
+            "plus" => Token::Plus, "minus" => Token::Minus, "times" => Token::Times, "divided" => Token::Divided,
+            "equal"|"equals" => Token::Equal, "less" => Token::Less, "greater" => Token::Greater,
+            "and" => Token::And, "or" => Token::Or, "not" => Token::Not, "is" => Token::Is, "than" => Token::Than,
+            "true" => Token::True, "false" => Token::False, "contains" => Token::Contains,
+            "if" => Token::If, "otherwise"|"else" => Token::Otherwise, "end" => Token::End,
+            "for" => Token::For, "each" => Token::Each, "repeat" => Token::Repeat,
+            "procedure" => Token::Procedure, "taking" => Token::Taking, "return" => Token::Return,
+            "then" => Token::Then,
 
             _ => Token::Identifier(text.to_string()),
         }
     }
     
-    fn read_string(&mut self) -> Token { self.advance(); let s = self.position; while let Some(c) = self.current_char() { if c == '"' { break; } self.advance(); } let t = &self.input[s..self.position]; self.advance(); Token::String(t.to_string()) }
-    fn read_number(&mut self) -> Token { let s = self.position; while let Some(c) = self.current_char() { if !c.is_digit(10) { break; } self.advance(); } let t = &self.input[s..self.position]; Token::Number(t.parse().unwrap_or(0)) }
-    fn skip_whitespace(&mut self) { while let Some(c) = self.current_char() { if !c.is_whitespace() { break; } self.advance(); } }
-    fn current_char(&self) -> Option<char> { self.input.chars().nth(self.position) }
-    fn advance(&mut self) { if self.position < self.input.len() { self.position += 1; } }
-}
\ No newline at end of file
+    // Reads a "double", 'single' or """triple-quoted""" string, decoding escapes.
+    // Only triple-quoted strings may span several lines.
+    fn read_string(&mut self, quote: char) -> Token {
+        let opening = self.here();
+        let triple = self.starts_with_repeated(quote, 3);
+        let delimiter_len = if triple { 3 } else { 1 };
+        for _ in 0..delimiter_len { self.advance(); }
+
+        let mut text = String::new();
+        loop {
+            match self.current_char() {
+                Some(c) if c == quote && (!triple || self.starts_with_repeated(quote, 3)) => {
+                    for _ in 0..delimiter_len { self.advance(); }
+                    return Token::String(text);
+                }
+                Some('\\') => {
+                    if let Some(c) = self.read_escape() { text.push(c); }
+                }
+                Some('\n') if !triple => break,
+                Some(c) => { text.push(c); self.advance(); }
+                None => { self.cut_short = triple; break; }
+            }
+        }
+
+        // Ran into the end of the line (or script) without finding the closing quote.
+        let closing = quote.to_string().repeat(delimiter_len);
+        let span = self.span_from(opening);
+        self.errors.push(Diagnostic::new(format!("This text is missing its closing `{}`.", closing), span)
+            .with_hint(if triple {
+                format!("add `{}` where the text should end", closing)
+            } else {
+                format!("add `{}` at the end of the text; use `{}` quotes for text that spans several lines", closing, quote.to_string().repeat(3))
+            }));
+        Token::Illegal(self.input[span.start..span.end].to_string())
+    }
+
+    // Decodes one escape sequence (the current char is the backslash). Unknown
+    // escapes are reported and kept as written.
+    fn read_escape(&mut self) -> Option<char> {
+        let start = self.here();
+        self.advance();
+        let escaped = self.current_char()?;
+        self.advance();
+        let decoded = match escaped {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '\\' | '"' | '\'' => Some(escaped),
+            'u' => self.read_unicode_escape(),
+            _ => None,
+        };
+        if decoded.is_none() {
+            let span = self.span_from(start);
+            let written = &self.input[span.start..span.end];
+            self.errors.push(Diagnostic::new(format!("`{}` is not a valid escape sequence.", written), span)
+                .with_hint("use `\\n`, `\\t`, `\\\"`, `\\'`, `\\\\` or `\\u{...}`; write `\\\\` for a plain backslash"));
+        }
+        decoded
+    }
+
+    // The `{1F600}` part of a `\u{1F600}` escape.
+    fn read_unicode_escape(&mut self) -> Option<char> {
+        if self.current_char() != Some('{') { return None; }
+        self.advance();
+        let mut hex = String::new();
+        while let Some(c) = self.current_char() {
+            if c == '}' { self.advance(); return u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32); }
+            if !c.is_ascii_hexdigit() || hex.len() == 6 { return None; }
+            hex.push(c);
+            self.advance();
+        }
+        None
+    }
+
+    fn starts_with_repeated(&self, c: char, count: usize) -> bool {
+        self.chars[self.index..].iter().take(count).filter(|&&(_, x)| x == c).count() == count
+    }
+    // Byte offset of the current character (or the end of the input).
+    fn offset(&self) -> usize { self.chars.get(self.index).map_or(self.input.len(), |&(offset, _)| offset) }
+    fn here(&self) -> (usize, usize, usize) { (self.offset(), self.line, self.column) }
+    fn span_from(&self, (start, line, column): (usize, usize, usize)) -> Span { Span::new(start, self.offset(), line, column) }
+    fn read_number(&mut self) -> Token {
+        let start = self.here();
+        self.skip_digits();
+        // `3.14` is a decimal, but in `count ... as n3.` the `.` ends the statement.
+        let is_decimal = self.current_char() == Some('.') && self.peek_char().is_some_and(|c| c.is_ascii_digit());
+        if is_decimal {
+            self.advance();
+            self.skip_digits();
+        }
+        let span = self.span_from(start);
+        let t = &self.input[span.start..span.end];
+        let token = if is_decimal {
+            t.parse().ok().filter(|f: &f64| f.is_finite()).map(Token::Float)
+        } else {
+            t.parse().ok().map(Token::Number)
+        };
+        token.unwrap_or_else(|| {
+            let diagnostic = Diagnostic::new("This number is too large.", span);
+            self.errors.push(if is_decimal { diagnostic } else { diagnostic.with_hint(format!("whole numbers go up to {}", i64::MAX)) });
+            Token::Illegal(t.to_string())
+        })
+    }
+    fn skip_digits(&mut self) { while let Some(c) = self.current_char() { if !c.is_ascii_digit() { break; } self.advance(); } }
+    // Skips spaces, `# comments` and line continuations, but not line breaks:
+    // those end statements, so they become `Token::Newline`. Inside brackets a
+    // long list or record may run over several lines, so line breaks are skipped too.
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.current_char() {
+            match c {
+                '\n' if self.bracket_depth == 0 => break,
+                '#' => self.skip_comment(),
+                '\\' if self.is_line_continuation() => {
+                    // A trailing `\` joins the next line onto this statement.
+                    self.advance();
+                    self.skip_whitespace();
+                    if self.current_char() == Some('\n') { self.advance(); }
+                    // Nothing after it yet: the statement goes on in input still to come.
+                    if self.current_char().is_none() { self.cut_short = true; }
+                }
+                _ if c.is_whitespace() => self.advance(),
+                _ => break,
+            }
+        }
+    }
+    fn skip_comment(&mut self) { while let Some(c) = self.current_char() { if c == '\n' { break; } self.advance(); } }
+    // True if the `\` at the current position is the last thing on its line (ignoring comments).
+    fn is_line_continuation(&self) -> bool {
+        for &(_, c) in &self.chars[self.index + 1..] {
+            match c {
+                '\n' | '#' => return true,
+                _ if c.is_whitespace() => continue,
+                _ => return false,
+            }
+        }
+        true
+    }
+    fn current_char(&self) -> Option<char> { self.chars.get(self.index).map(|&(_, c)| c) }
+    fn peek_char(&self) -> Option<char> { self.chars.get(self.index + 1).map(|&(_, c)| c) }
+    fn advance(&mut self) {
+        if let Some(c) = self.current_char() {
+            if c == '\n' { self.line += 1; self.column = 1; } else { self.column += 1; }
+        }
+        if self.index < self.chars.len() { self.index += 1; }
+    }
+}
+
+// Letters, digits and `_`, plus the combining marks that many scripts need
+// inside a word (e.g. the virama in Devanagari "नमस्ते", or decomposed accents).
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || is_combining_mark(c)
+}
+
+fn is_combining_mark(c: char) -> bool {
+    matches!(c as u32,
+        0x0300..=0x036F     // Combining Diacritical Marks
+        | 0x0483..=0x0489   // Cyrillic
+        | 0x0591..=0x05C7   // Hebrew points
+        | 0x0610..=0x061A | 0x064B..=0x065F | 0x0670 // Arabic
+        | 0x0900..=0x0963 | 0x0966..=0x0DFF // Indic scripts, except the danda punctuation
+        | 0x0E31 | 0x0E34..=0x0E3A | 0x0E47..=0x0E4E // Thai
+        | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF | 0xFE20..=0xFE2F
+    )
+}
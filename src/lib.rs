@@ -1,6 +1,7 @@
 // src/lib.rs
 
 pub mod ast;
+pub mod diagnostics;
 pub mod lexer;
 pub mod parser;
 pub mod runtime;
\ No newline at end of file
@@ -1,52 +1,147 @@
 // src/main.rs
 
+use std::env;
+use std::fs;
 use std::io::{self, Write};
+use std::process::{self, ExitCode};
+use talea::diagnostics::Diagnostic;
 // Bring the new Interpreter into scope
-use talea::runtime::interpreter::Interpreter;
+use talea::runtime::interpreter::{ControlFlow, Interpreter};
 
-fn main() {
+// Exit codes used when running a script, so batch jobs (cron, make) can tell
+// what went wrong without parsing our output.
+const EXIT_RUNTIME_ERROR: u8 = 1;
+const EXIT_PARSE_ERROR: u8 = 2;
+const EXIT_USAGE_ERROR: u8 = 64;
+const EXIT_IO_ERROR: u8 = 66;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    // `talea` starts the REPL, `talea run script.tea` and `talea script.tea` run a file.
+    match args.as_slice() {
+        [] => {
+            run_repl();
+            ExitCode::SUCCESS
+        }
+        [command, path] if command == "run" => run_file(path),
+        [path] if path != "run" => run_file(path),
+        _ => {
+            eprintln!("Usage: talea [run] <script.tea>");
+            ExitCode::from(EXIT_USAGE_ERROR)
+        }
+    }
+}
+
+// Runs a whole script in one go. Stops at the first error and reports it
+// through the process exit code.
+fn run_file(path: &str) -> ExitCode {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Error: Could not read script '{}': {}", path, e);
+            return ExitCode::from(EXIT_IO_ERROR);
+        }
+    };
+
+    // 1. Lexing
+    let mut lexer = talea::lexer::Lexer::new(&source);
+    let tokens = lexer.all_tokens();
+
+    // 2. Parsing the whole file before running anything, so a typo on the
+    //    last line doesn't leave a half-finished pipeline behind. Every
+    //    syntax error in the file is reported together.
+    let mut parser = talea::parser::Parser::new(tokens);
+    let output = parser.parse();
+    if !lexer.errors().is_empty() || !output.is_ok() {
+        report_syntax_errors(lexer.errors(), output.diagnostics, &source);
+        return ExitCode::from(EXIT_PARSE_ERROR);
+    }
+    let ast = output.statements;
+
+    // 3. Interpreting
+    let mut interpreter = Interpreter::new();
+    if let Err(e) = interpreter.execute(ast) {
+        report("Runtime Error", &[e], &source);
+        return ExitCode::from(EXIT_RUNTIME_ERROR);
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn run_repl() {
     println!("talea REPL v0.1.0");
 
     // Create the interpreter. It holds the state (like variables).
     // It lives for the entire REPL session.
     let mut interpreter = Interpreter::new();
 
+    // Lines are collected until every block, bracket, triple-quoted string and
+    // `\` continuation they open is closed, then run together.
+    let mut buffer = String::new();
     loop {
-        print!("> ");
+        print!("{}", if buffer.is_empty() { "> " } else { "... " });
         io::stdout().flush().unwrap();
 
-        let mut input = String::new();
-        if io::stdin().read_line(&mut input).is_err() {
-            break;
+        let mut line = String::new();
+        // `read_line` returns Ok(0) at end of input (e.g. Ctrl-D or a closed pipe).
+        match io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
         }
 
-        let input = input.trim();
-        if input == "exit" {
-            break;
-        }
-        
-        if input.is_empty() {
-            continue;
+        if buffer.is_empty() {
+            let line = line.trim();
+            if line == "exit" {
+                break;
+            }
+            if line.is_empty() {
+                continue;
+            }
         }
+        buffer.push_str(&line);
 
         // 1. Lexing
-        let mut lexer = talea::lexer::Lexer::new(input);
+        let mut lexer = talea::lexer::Lexer::new(&buffer);
         let tokens = lexer.all_tokens();
+        if lexer.is_unfinished() || talea::parser::open_blocks(&tokens) > 0 {
+            continue; // Wait for the rest of the statement
+        }
+        let lexer_errors = lexer.errors().to_vec();
+        let input = std::mem::take(&mut buffer);
 
         // 2. Parsing
         let mut parser = talea::parser::Parser::new(tokens);
-        let ast = match parser.parse() {
-            Ok(ast) => ast,
-            Err(e) => {
-                eprintln!("Parse Error: {}", e);
-                continue; // Skip to the next loop iteration
-            }
-        };
+        let output = parser.parse();
+        if !lexer_errors.is_empty() || !output.is_ok() {
+            report_syntax_errors(&lexer_errors, output.diagnostics, &input);
+            continue; // Skip to the next loop iteration
+        }
+        let ast = output.statements;
 
         // 3. INTERPRETING!
         // The interpreter executes the AST.
-        if let Err(e) = interpreter.execute(ast) {
-            eprintln!("Runtime Error: {}", e);
+        match interpreter.execute(ast) {
+            Ok(ControlFlow::Exit) => process::exit(0),
+            Ok(ControlFlow::Continue) => {}
+            Err(e) => report("Runtime Error", &[e], &input),
         }
     }
-}
\ No newline at end of file
+}
+
+// Lexer and parser errors are reported together, in source order.
+fn report_syntax_errors(lexer_errors: &[Diagnostic], parser_errors: Vec<Diagnostic>, source: &str) {
+    let mut errors: Vec<Diagnostic> = lexer_errors.iter().cloned().chain(parser_errors).collect();
+    errors.sort_by_key(|e| e.span.start);
+    report("Parse Error", &errors, source);
+    if errors.len() > 1 {
+        eprintln!("Found {} errors.", errors.len());
+    }
+}
+
+// Prints each error with the offending source line and a caret under the problem.
+fn report(kind: &str, diagnostics: &[Diagnostic], source: &str) {
+    for diagnostic in diagnostics {
+        eprint!("{}", diagnostic.render(kind, source));
+    }
+}
@@ -1,16 +1,166 @@
 // src/parser.rs
 
-use crate::ast::{ArithmeticOp, Backend, Expression, FilterCondition, Statement};
+use std::rc::Rc;
+
+use crate::ast::{IMPLICIT_RESULT, ArithmeticOp, Backend, CleaningStep, ComparisonOp, ContextSide, Expression, FilterCondition, LogicalOp, SliceEnd, SortKey, Statement};
+use crate::diagnostics::{suggest, Diagnostic, Span, Spanned};
 use crate::lexer::Token;
 
-pub struct Parser { tokens: Vec<Token>, position: usize }
+// Every command keyword a statement can start with, used for "did you mean" hints.
+const COMMANDS: &[&str] = &[
+    "use", "summarize", "lemmatize", "filter", "load", "save", "print", "tokenize", "count", "tag", "define", "set", "assign",
+    "add", "subtract", "multiply", "divide", "exit", "if", "for", "repeat", "return", "select", "sort", "frequency", "rank",
+    "concordance", "kwic", "collocate", "extract", "find", "replace",
+    "lowercase", "uppercase", "remove", "normalize", "clean",
+];
+
+// Everything the parser produced: the statements that parsed cleanly and a
+// diagnostic for each one that didn't.
+pub struct ParseOutput {
+    pub statements: Vec<Spanned<Statement>>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ParseOutput {
+    pub fn is_ok(&self) -> bool { self.diagnostics.is_empty() }
+}
+
+// How many `if`, `for each`, `repeat` and `define procedure` blocks are still
+// waiting for their `end`. The REPL keeps reading lines until this is zero.
+pub fn open_blocks(tokens: &[Spanned<Token>]) -> usize {
+    let mut depth = 0usize;
+    let mut previous: Option<&Token> = None;
+    for token in tokens {
+        match (&token.node, previous) {
+            // `otherwise if` continues the block its `if` opened.
+            (Token::If, Some(Token::Otherwise)) => {}
+            (Token::If | Token::For | Token::Repeat, _) | (Token::Procedure, Some(Token::Define)) => depth += 1,
+            (Token::End, _) => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        previous = Some(&token.node);
+    }
+    depth
+}
+
+pub struct Parser { tokens: Vec<Spanned<Token>>, position: usize, diagnostics: Vec<Diagnostic> }
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self { Parser { tokens, position: 0 } }
-    pub fn parse(&mut self) -> Result<Vec<Statement>, String> { let mut stmts = Vec::new(); while !self.is_at_end() { stmts.push(self.parse_statement()?); } Ok(stmts) }
+    pub fn new(tokens: Vec<Spanned<Token>>) -> Self { Parser { tokens, position: 0, diagnostics: Vec::new() } }
+
+    // Parses the whole script. After an error the parser skips ahead to the next
+    // command keyword and carries on, so every syntax error is reported in one run.
+    pub fn parse(&mut self) -> ParseOutput {
+        let statements = self.parse_statements_until(&[]);
+        ParseOutput { statements, diagnostics: std::mem::take(&mut self.diagnostics) }
+    }
+
+    // Parses statements up to (not including) one of `terminators`, e.g. the
+    // `end` of a block, recording errors as it goes.
+    fn parse_statements_until(&mut self, terminators: &[Token]) -> Vec<Spanned<Statement>> {
+        let mut statements = Vec::new();
+        loop {
+            self.skip_separators();
+            if self.is_at_end() || self.current_token().is_some_and(|t| terminators.contains(t)) { break; }
+            match self.parse_complete_statement() {
+                Ok(statement) => statements.push(statement),
+                Err(diagnostic) => {
+                    // Illegal characters have already been reported by the lexer.
+                    if !matches!(self.current_token(), Some(Token::Illegal(_))) {
+                        self.diagnostics.push(diagnostic);
+                    }
+                    self.synchronize();
+                }
+            }
+        }
+        statements
+    }
+
+    // A statement (or a pipeline of them) followed by a line break, `.`, `;` or
+    // the end of the script.
+    fn parse_complete_statement(&mut self) -> Result<Spanned<Statement>, Diagnostic> {
+        let start = self.current_span();
+        let first = self.parse_statement()?;
+        let mut span = start.to(self.previous_span());
+        let statement = if self.continues_pipeline() {
+            let mut stages = vec![Spanned::new(first, span)];
+            while self.continues_pipeline() {
+                self.skip_separators();
+                self.advance(); // consume 'then'
+                let stage_start = self.current_span();
+                let stage = self.parse_pipeline_stage()?;
+                stages.push(Spanned::new(stage, stage_start.to(self.previous_span())));
+            }
+            span = start.to(self.previous_span());
+            Statement::Pipeline(stages)
+        } else {
+            first
+        };
+        match self.current_token() {
+            Some(Token::Newline | Token::Terminator) => self.advance(),
+            // A block's `otherwise`/`end` may follow on the same line: `if x print x end`.
+            Some(Token::Eof | Token::Otherwise | Token::End) | None => {}
+            Some(_) => return Err(self.expected("the end of the statement")
+                .with_hint("put each statement on its own line, or separate them with `;`")),
+        }
+        Ok(Spanned::new(statement, span))
+    }
+
+    // `then` continues a pipeline, either on the same line or at the start of
+    // the next one.
+    fn continues_pipeline(&self) -> bool {
+        self.tokens[self.position..].iter()
+            .find(|token| token.node != Token::Newline)
+            .is_some_and(|token| token.node == Token::Then)
+    }
+
+    // A stage after `then`. A procedure called here gets the previous result as
+    // its first argument: `then clean_text with "en"`.
+    fn parse_pipeline_stage(&mut self) -> Result<Statement, Diagnostic> {
+        if matches!(self.current_token(), Some(Token::Newline | Token::Terminator | Token::Eof) | None) {
+            return Err(self.error("Expected a command after `then`.")
+                .with_hint("each `then` is followed by the next step, e.g. `then tokenize`"));
+        }
+        match self.parse_statement()? {
+            Statement::Call { name, mut arguments, destination } => {
+                arguments.insert(0, Expression::Identifier(IMPLICIT_RESULT.to_string()));
+                Ok(Statement::Call { name, arguments, destination })
+            }
+            Statement::Pipeline(_) => unreachable!("stages are single statements"),
+            stage => Ok(stage),
+        }
+    }
+
+    // Skips to the start of the next statement: the next line, the next `.`/`;`,
+    // or the next command keyword, whichever comes first.
+    fn synchronize(&mut self) {
+        while let Some(token) = self.current_token() {
+            if self.is_at_end() { break; }
+            let is_separator = matches!(token, Token::Newline | Token::Terminator);
+            self.advance();
+            let at_block_boundary = matches!(self.current_token(), Some(Token::Otherwise | Token::End));
+            if is_separator || at_block_boundary || self.current_token().is_some_and(Self::is_statement_start) { break; }
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while matches!(self.current_token(), Some(Token::Newline | Token::Terminator)) { self.advance(); }
+    }
 
-    fn parse_statement(&mut self) -> Result<Statement, String> {
+    fn is_statement_start(token: &Token) -> bool {
+        matches!(token, Token::Use | Token::Summarize | Token::Lemmatize | Token::Filter | Token::Load | Token::Save
+            | Token::Print | Token::Tokenize | Token::Count | Token::Tag | Token::Define | Token::Set | Token::Assign | Token::Add | Token::Subtract
+            | Token::Multiply | Token::Divide | Token::Exit | Token::Quit | Token::If | Token::For | Token::Repeat | Token::Return
+            | Token::Select | Token::Sort | Token::Frequency | Token::Rank | Token::Concordance | Token::KWIC | Token::Collocate | Token::Find | Token::Replace
+            | Token::Lowercase | Token::Uppercase | Token::Remove | Token::Normalize | Token::Clean)
+    }
+
+    fn parse_statement(&mut self) -> Result<Statement, Diagnostic> {
         match self.current_token().cloned() {
             Some(Token::Use) => self.parse_use_statement(),
+            Some(Token::If) => self.parse_if_statement(),
+            Some(Token::For) => self.parse_for_each_statement(),
+            Some(Token::Repeat) => self.parse_repeat_statement(),
+            Some(Token::Return) => self.parse_return_statement(),
             Some(Token::Summarize) => self.parse_summarize_statement(),
             Some(Token::Lemmatize) => self.parse_lemmatize_statement(),
             Some(Token::Filter) => self.parse_filter_statement(),
@@ -19,76 +169,523 @@ impl Parser {
             Some(Token::Print) => self.parse_print_statement(),
             Some(Token::Tokenize) => self.parse_tokenize_statement(),
             Some(Token::Count) => self.parse_count_statement(),
+            Some(Token::Select) => self.parse_select_statement(),
+            Some(Token::Sort) => self.parse_sort_statement(),
+            Some(Token::Frequency) => self.parse_frequency_statement(),
+            Some(Token::Rank) => self.parse_rank_statement(),
+            Some(Token::Concordance | Token::KWIC) => self.parse_concordance_statement(),
+            Some(Token::Collocate) => self.parse_collocate_statement(),
+            Some(Token::Find) => self.parse_find_statement(),
+            Some(Token::Replace) => self.parse_replace_statement(),
+            Some(Token::Lowercase | Token::Uppercase) => self.parse_change_case_statement(),
+            Some(Token::Remove) => self.parse_remove_statement(),
+            Some(Token::Normalize) => self.parse_normalize_statement(),
+            Some(Token::Clean) => self.parse_clean_statement(),
             Some(Token::Tag) => self.parse_tag_statement(),
             Some(Token::Define) => self.parse_define_statement(),
+            Some(Token::Set) => self.parse_set_statement(),
+            Some(Token::Assign) => self.parse_assign_statement(),
             Some(Token::Add) => self.parse_arithmetic_statement(ArithmeticOp::Add),
             Some(Token::Subtract) => self.parse_arithmetic_statement(ArithmeticOp::Subtract),
             Some(Token::Multiply) => self.parse_arithmetic_statement(ArithmeticOp::Multiply),
             Some(Token::Divide) => self.parse_arithmetic_statement(ArithmeticOp::Divide),
-            Some(Token::Exit) | Some(Token::Quit) => std::process::exit(0),
-            Some(t) => Err(format!("Unrecognized or unimplemented command: {:?}", t)),
-            None => Err("Unexpected end of input".to_string()),
+            Some(Token::Exit) | Some(Token::Quit) => { self.advance(); Ok(Statement::Exit) },
+            Some(Token::Identifier(_)) if self.is_call_start() => self.parse_call_statement(),
+            Some(Token::Identifier(name)) => {
+                let error = self.error(format!("`{}` is not a command Talea knows.", name));
+                Err(match suggest(&name, COMMANDS.iter().copied()) {
+                    Some(command) => error.with_hint(format!("did you mean `{}`?", command)),
+                    None => error.with_hint("every statement starts with a command such as `load`, `print` or `define`"),
+                })
+            }
+            Some(Token::Otherwise) => Err(self.error("Found `otherwise` without a matching `if`.")),
+            Some(Token::End) => Err(self.error("Found `end` without a block to close.")
+                .with_hint("`end` closes an `if`, `for each`, `repeat` or `procedure` block")),
+            Some(t) => Err(self.error(format!("A statement can't start with {}.", t.describe()))
+                .with_hint("every statement starts with a command such as `load`, `print` or `define`")),
+            None => Err(self.error("The script ended unexpectedly.")),
         }
     }
 
+    // `if <condition> ... [otherwise [if <condition>] ...] end`
+    fn parse_if_statement(&mut self) -> Result<Statement, Diagnostic> {
+        let if_span = self.current_span();
+        self.advance(); // consume 'if'
+        let condition = self.parse_expression()?;
+        let then_branch = self.parse_statements_until(&[Token::Otherwise, Token::End]);
+        let else_branch = if self.current_token() == Some(&Token::Otherwise) {
+            self.advance();
+            if self.current_token() == Some(&Token::If) {
+                // `otherwise if` chains share the final `end`, so the nested `if` consumes it.
+                let start = self.current_span();
+                let nested = self.parse_if_statement()?;
+                return Ok(Statement::If { condition, then_branch, else_branch: vec![Spanned::new(nested, start.to(self.previous_span()))] });
+            }
+            self.parse_statements_until(&[Token::End])
+        } else {
+            Vec::new()
+        };
+        self.consume_block_end("if", if_span)?;
+        Ok(Statement::If { condition, then_branch, else_branch })
+    }
+
+    // `for each <name> in <list> ... end`
+    fn parse_for_each_statement(&mut self) -> Result<Statement, Diagnostic> {
+        let for_span = self.current_span();
+        self.advance(); // consume 'for'
+        self.consume(Token::Each)?;
+        let variable = self.parse_identifier_expression()?;
+        self.consume(Token::In)?;
+        let iterable = self.parse_expression()?;
+        let body = self.parse_statements_until(&[Token::End]);
+        self.consume_block_end("for each", for_span)?;
+        Ok(Statement::ForEach { variable, iterable, body })
+    }
+
+    // `repeat <n> times ... end`
+    fn parse_repeat_statement(&mut self) -> Result<Statement, Diagnostic> {
+        let repeat_span = self.current_span();
+        self.advance(); // consume 'repeat'
+        // A single value, since `times` would otherwise read as multiplication:
+        // `repeat (n + 1) times`.
+        let count = self.parse_unary()?;
+        self.consume(Token::Times)?;
+        let body = self.parse_statements_until(&[Token::End]);
+        self.consume_block_end("repeat", repeat_span)?;
+        Ok(Statement::Repeat { count, body })
+    }
+
+    // `define procedure <name> [taking <param>, <param> ...] ... end`
+    fn parse_procedure_definition(&mut self, define_span: Span) -> Result<Statement, Diagnostic> {
+        self.advance(); // consume 'procedure'
+        let name = self.parse_identifier_expression()?;
+        let mut parameters = Vec::new();
+        if self.current_token() == Some(&Token::Taking) {
+            self.advance();
+            loop {
+                match self.parse_identifier_expression()? {
+                    Expression::Identifier(parameter) => parameters.push(parameter),
+                    _ => unreachable!("parse_identifier_expression only returns identifiers"),
+                }
+                // Parameters may be separated by commas or by `and`.
+                if !matches!(self.current_token(), Some(Token::Comma | Token::And)) { break; }
+                self.advance();
+            }
+        }
+        let body = self.parse_statements_until(&[Token::End]);
+        self.consume_block_end("procedure", define_span)?;
+        Ok(Statement::DefineProcedure { name, parameters, body: Rc::new(body) })
+    }
+
+    // A statement that starts with a name calls the procedure of that name:
+    // `clean_text`, or `clean_text with a, b as result`.
+    fn is_call_start(&self) -> bool {
+        matches!(self.peek_token(), Some(Token::With | Token::As | Token::Then | Token::Newline | Token::Terminator | Token::Eof | Token::Otherwise | Token::End) | None)
+    }
+
+    fn parse_call_statement(&mut self) -> Result<Statement, Diagnostic> {
+        let name = self.parse_identifier_expression()?;
+        let mut arguments = Vec::new();
+        if self.current_token() == Some(&Token::With) {
+            self.advance();
+            arguments.push(self.parse_expression()?);
+            while self.current_token() == Some(&Token::Comma) {
+                self.advance();
+                arguments.push(self.parse_expression()?);
+            }
+        }
+        let destination = if self.current_token() == Some(&Token::As) { self.advance(); Some(self.parse_identifier_expression()?) } else { None };
+        Ok(Statement::Call { name, arguments, destination })
+    }
+
+    fn parse_return_statement(&mut self) -> Result<Statement, Diagnostic> {
+        self.advance(); // consume 'return'
+        match self.current_token() {
+            Some(Token::Newline | Token::Terminator | Token::Eof | Token::Otherwise | Token::End | Token::Then) | None => Ok(Statement::Return(None)),
+            _ => Ok(Statement::Return(Some(self.parse_expression()?))),
+        }
+    }
+
+    fn consume_block_end(&mut self, block: &str, start: Span) -> Result<(), Diagnostic> {
+        if self.current_token() != Some(&Token::End) {
+            return Err(Diagnostic::new(format!("This `{}` block is never closed.", block), start)
+                .with_hint("add `end` on its own line after the last statement of the block"));
+        }
+        self.advance(); // consume 'end'
+        Ok(())
+    }
+
     // Command Parsers with Context-Aware Logic
-    fn parse_load_statement(&mut self) -> Result<Statement, String> {
+    fn parse_load_statement(&mut self) -> Result<Statement, Diagnostic> {
         self.advance();
+        // `load stopwords from "my_list.txt" [as name]`
+        if self.current_token() == Some(&Token::Stopwords) {
+            self.advance();
+            self.consume(Token::From)?;
+            let path = self.parse_expression()?;
+            let destination = self.parse_destination()?;
+            return Ok(Statement::LoadStopwords { path, destination });
+        }
         let source = self.parse_expression()?;
-        self.consume(Token::As)?;
-        let alias = self.parse_identifier_expression()?;
+        let alias = self.parse_destination()?;
         Ok(Statement::Load { source, alias })
     }
 
-    fn parse_tokenize_statement(&mut self) -> Result<Statement, String> {
+    fn parse_tokenize_statement(&mut self) -> Result<Statement, Diagnostic> {
         self.advance();
-        let source = self.parse_expression()?;
-        self.consume(Token::As)?;
-        let destination = self.parse_identifier_expression()?;
+        let source = self.parse_source()?;
+        let destination = self.parse_destination()?;
         Ok(Statement::Tokenize { source, destination })
     }
     
-    fn parse_tag_statement(&mut self) -> Result<Statement, String> {
+    fn parse_tag_statement(&mut self) -> Result<Statement, Diagnostic> {
         self.advance(); // consume 'tag'
-        let source = self.parse_expression()?;
+        let source = self.parse_source()?;
         self.consume(Token::With)?;
         let method = self.parse_unit_expression()?;
-        self.consume(Token::As)?;
         // FIX: The destination MUST be a variable name (an identifier).
-        let destination = self.parse_identifier_expression()?;
+        let destination = self.parse_destination()?;
         Ok(Statement::Tag { source, method, destination })
 
     }
 
-    fn parse_count_statement(&mut self) -> Result<Statement, String> {
+    fn parse_count_statement(&mut self) -> Result<Statement, Diagnostic> {
         self.advance();
         let unit = self.parse_unit_expression()?;
-        self.consume(Token::In)?;
-        let source = self.parse_identifier_expression()?;
-        self.consume(Token::As)?;
-        let destination = self.parse_identifier_expression()?;
+        // `count words` on its own counts the previous result.
+        let source = if self.current_token() == Some(&Token::In) { self.advance(); self.parse_identifier_expression()? } else { Self::implicit_result() };
+        let destination = self.parse_destination()?;
         Ok(Statement::Count { unit, source, destination })
     }
 
-    fn parse_define_statement(&mut self) -> Result<Statement, String> {
+    // `select text, label from entities as t`
+    fn parse_select_statement(&mut self) -> Result<Statement, Diagnostic> {
+        self.advance();
+        let mut columns = vec![self.parse_column_name()?];
+        while self.current_token() == Some(&Token::Comma) {
+            self.advance();
+            columns.push(self.parse_column_name()?);
+        }
+        let source = if self.current_token() == Some(&Token::From) { self.advance(); self.parse_expression()? } else { Self::implicit_result() };
+        let destination = self.parse_destination()?;
+        Ok(Statement::Select { columns, source, destination })
+    }
+
+    // `sort words descending as sorted`
+    // `sort freq by count descending, word ascending as ranked`
+    fn parse_sort_statement(&mut self) -> Result<Statement, Diagnostic> {
         self.advance();
+        let source = self.parse_source()?;
+        let mut keys = Vec::new();
+        let mut descending = false;
+        if self.current_token() == Some(&Token::By) {
+            self.advance();
+            loop {
+                let column = self.parse_column_name()?;
+                keys.push(SortKey { column, descending: self.parse_sort_direction() });
+                // Further keys follow a comma or `and`.
+                if !matches!(self.current_token(), Some(Token::Comma | Token::And)) { break; }
+                self.advance();
+            }
+        } else {
+            descending = self.parse_sort_direction();
+        }
+        let destination = self.parse_destination()?;
+        Ok(Statement::Sort { source, keys, descending, destination })
+    }
+
+    // An optional `ascending` or `descending`; ascending if neither is given.
+    fn parse_sort_direction(&mut self) -> bool {
+        match self.current_token() {
+            Some(Token::Descending) => { self.advance(); true }
+            Some(Token::Ascending) => { self.advance(); false }
+            _ => false,
+        }
+    }
+
+    // `frequency of words in tokens [ignoring case] as freq`
+    fn parse_frequency_statement(&mut self) -> Result<Statement, Diagnostic> {
+        self.advance();
+        self.consume(Token::Of)?;
+        let unit = self.parse_unit_expression()?;
+        let source = if self.current_token() == Some(&Token::In) { self.advance(); self.parse_expression()? } else { Self::implicit_result() };
+        let ignore_case = self.parse_ignoring_case();
+        let destination = self.parse_destination()?;
+        Ok(Statement::Frequency { unit, source, ignore_case, destination })
+    }
+
+    // `rank scores by score [ascending] as ranked`: highest first unless `ascending`.
+    fn parse_rank_statement(&mut self) -> Result<Statement, Diagnostic> {
+        self.advance();
+        let source = self.parse_source()?;
+        self.consume(Token::By)?;
+        let key = self.parse_column_name()?;
+        let ascending = match self.current_token() {
+            Some(Token::Ascending) => { self.advance(); true }
+            Some(Token::Descending) => { self.advance(); false }
+            _ => false,
+        };
+        let destination = self.parse_destination()?;
+        Ok(Statement::Rank { source, key, ascending, destination })
+    }
+
+    // `concordance "love" in novel [width 40] [sorted by left|right] [as lines]`
+    fn parse_concordance_statement(&mut self) -> Result<Statement, Diagnostic> {
+        self.advance();
+        let keyword = self.parse_expression()?;
+        let source = if self.current_token() == Some(&Token::In) { self.advance(); self.parse_expression()? } else { Self::implicit_result() };
+        let width = if self.at_word("width") { self.advance(); Some(self.parse_expression()?) } else { None };
+        let sorted_by = if self.at_word("sorted") {
+            self.advance();
+            self.consume(Token::By)?;
+            let side = if self.at_word("left") {
+                ContextSide::Left
+            } else if self.at_word("right") {
+                ContextSide::Right
+            } else {
+                return Err(self.expected("`left` or `right`"));
+            };
+            self.advance();
+            Some(side)
+        } else {
+            None
+        };
+        let destination = self.parse_destination()?;
+        Ok(Statement::Concordance { keyword, source, width, sorted_by, destination })
+    }
+
+    // `find pattern "colou?r" in text [ignoring case] [as hits]`, or n-grams
+    // (`extract`, `search` and `match` are other spellings of `find`).
+    fn parse_find_statement(&mut self) -> Result<Statement, Diagnostic> {
+        if matches!(self.peek_token(), Some(Token::Bigrams | Token::Trigrams | Token::Ngrams)) {
+            return self.parse_ngram_statement();
+        }
+        self.advance();
+        let literal = !matches!(self.current_token(), Some(Token::Pattern | Token::Regex));
+        if !literal { self.advance(); }
+        let pattern = self.parse_expression()?;
+        let source = if self.current_token() == Some(&Token::In) { self.advance(); self.parse_expression()? } else { Self::implicit_result() };
+        let ignore_case = self.parse_ignoring_case();
+        let destination = self.parse_destination()?;
+        Ok(Statement::Find { pattern, literal, source, ignore_case, destination })
+    }
+
+    // `replace [regex] "ſ" with "s" [in text] [ignoring case] [as modern]`
+    fn parse_replace_statement(&mut self) -> Result<Statement, Diagnostic> {
+        self.advance();
+        let literal = !matches!(self.current_token(), Some(Token::Pattern | Token::Regex));
+        if !literal { self.advance(); }
+        let pattern = self.parse_expression()?;
+        self.consume(Token::With)?;
+        let replacement = self.parse_expression()?;
+        let source = if self.current_token() == Some(&Token::In) { self.advance(); self.parse_expression()? } else { Self::implicit_result() };
+        let ignore_case = self.parse_ignoring_case();
+        let destination = self.parse_destination()?;
+        Ok(Statement::Replace { pattern, replacement, literal, source, ignore_case, destination })
+    }
+
+    // `lowercase text [as lower]`, `uppercase text`
+    fn parse_change_case_statement(&mut self) -> Result<Statement, Diagnostic> {
+        let upper = self.current_token() == Some(&Token::Uppercase);
+        self.advance();
+        let source = self.parse_source()?;
+        let destination = self.parse_destination()?;
+        Ok(Statement::ChangeCase { source, upper, destination })
+    }
+
+    // `remove punctuation|numbers|stopwords|whitespace [from tokens] [as d]`
+    fn parse_remove_statement(&mut self) -> Result<Statement, Diagnostic> {
+        self.advance();
+        let what = match self.current_token() {
+            Some(Token::Punctuation) => CleaningStep::Punctuation,
+            Some(Token::Numbers) => CleaningStep::Numbers,
+            Some(Token::Stopwords) => CleaningStep::Stopwords,
+            Some(Token::Whitespace) => CleaningStep::Whitespace,
+            _ => return Err(self.expected("`punctuation`, `numbers`, `stopwords` or `whitespace`")),
+        };
+        self.advance();
+        let stopword_list = if what == CleaningStep::Stopwords { self.parse_stopword_list()? } else { None };
+        let source = if self.current_token() == Some(&Token::From) { self.advance(); self.parse_expression()? } else { Self::implicit_result() };
+        let destination = self.parse_destination()?;
+        Ok(Statement::Remove { what, stopword_list, source, destination })
+    }
+
+    // The `in latin` after `stopwords`: a language or a variable holding a list.
+    fn parse_stopword_list(&mut self) -> Result<Option<String>, Diagnostic> {
+        if self.current_token() != Some(&Token::In) { return Ok(None); }
+        self.advance();
+        match self.parse_identifier_expression() {
+            Ok(Expression::Identifier(name)) => Ok(Some(name)),
+            _ => Err(self.expected("a language (like `latin`) or a list of stopwords")),
+        }
+    }
+
+    // `normalize text [to nfc|nfkc] [as d]`
+    fn parse_normalize_statement(&mut self) -> Result<Statement, Diagnostic> {
+        self.advance();
+        let source = self.parse_source()?;
+        let compatibility = if self.current_token() == Some(&Token::To) {
+            self.advance();
+            let compatibility = if self.at_word("nfkc") {
+                true
+            } else if self.at_word("nfc") {
+                false
+            } else {
+                return Err(self.expected("`nfc` or `nfkc`"));
+            };
+            self.advance();
+            compatibility
+        } else {
+            false
+        };
+        let destination = self.parse_destination()?;
+        Ok(Statement::Normalize { source, compatibility, destination })
+    }
+
+    // `clean text [with normalize, lowercase, punctuation, numbers, stopwords [in latin], whitespace] [as d]`
+    fn parse_clean_statement(&mut self) -> Result<Statement, Diagnostic> {
+        self.advance();
+        let source = self.parse_source()?;
+        let mut steps = Vec::new();
+        let mut stopword_list = None;
+        if self.current_token() == Some(&Token::With) {
+            self.advance();
+            loop {
+                let step = match self.current_token() {
+                    Some(Token::Normalize) => CleaningStep::Normalize,
+                    Some(Token::Lowercase) => CleaningStep::Lowercase,
+                    Some(Token::Punctuation) => CleaningStep::Punctuation,
+                    Some(Token::Numbers) => CleaningStep::Numbers,
+                    Some(Token::Stopwords) => CleaningStep::Stopwords,
+                    Some(Token::Whitespace) => CleaningStep::Whitespace,
+                    _ => return Err(self.expected("a cleaning step (`normalize`, `lowercase`, `punctuation`, `numbers`, `stopwords` or `whitespace`)")),
+                };
+                self.advance();
+                if step == CleaningStep::Stopwords { stopword_list = self.parse_stopword_list()?; }
+                if !steps.contains(&step) { steps.push(step); }
+                if !matches!(self.current_token(), Some(Token::Comma | Token::And)) { break; }
+                self.advance();
+            }
+        }
+        let destination = self.parse_destination()?;
+        Ok(Statement::Clean { source, steps, stopword_list, destination })
+    }
+
+    // An optional `ignoring case`.
+    fn parse_ignoring_case(&mut self) -> bool {
+        let found = self.at_word("ignoring") && matches!(self.peek_token(), Some(Token::Identifier(word)) if word.eq_ignore_ascii_case("case"));
+        if found { self.advance(); self.advance(); }
+        found
+    }
+
+    // `extract bigrams from tokens [skipping stopwords] [with counts] [as pairs]`
+    // `extract ngrams of size 4 from tokens`
+    fn parse_ngram_statement(&mut self) -> Result<Statement, Diagnostic> {
+        self.advance();
+        let size = match self.current_token() {
+            Some(Token::Bigrams) => { self.advance(); Expression::Number(2) }
+            Some(Token::Trigrams) => { self.advance(); Expression::Number(3) }
+            _ => {
+                self.advance();
+                self.consume(Token::Of)?;
+                if !self.at_word("size") { return Err(self.expected("`size`, as in `ngrams of size 4`")); }
+                self.advance();
+                self.parse_expression()?
+            }
+        };
+        let source = if self.current_token() == Some(&Token::From) { self.advance(); self.parse_expression()? } else { Self::implicit_result() };
+        let (mut skip_stopwords, mut counted) = (false, false);
+        let mut stopword_list = None;
+        loop {
+            if self.at_word("skipping") && self.peek_token() == Some(&Token::Stopwords) {
+                self.advance(); self.advance();
+                skip_stopwords = true;
+                stopword_list = self.parse_stopword_list()?;
+            } else if self.current_token() == Some(&Token::With) && matches!(self.peek_token(), Some(Token::Identifier(word)) if word.eq_ignore_ascii_case("counts")) {
+                self.advance(); self.advance();
+                counted = true;
+            } else {
+                break;
+            }
+        }
+        let destination = self.parse_destination()?;
+        Ok(Statement::Ngrams { size, source, skip_stopwords, stopword_list, counted, destination })
+    }
+
+    // `collocate "freedom" in corpus [window 5] [by mi] [as colls]`
+    fn parse_collocate_statement(&mut self) -> Result<Statement, Diagnostic> {
+        self.advance();
+        let node = self.parse_expression()?;
+        let source = if self.current_token() == Some(&Token::In) { self.advance(); self.parse_expression()? } else { Self::implicit_result() };
+        let window = if self.at_word("window") { self.advance(); Some(self.parse_expression()?) } else { None };
+        let ranked_by = if self.current_token() == Some(&Token::By) { self.advance(); Some(self.parse_column_name()?) } else { None };
+        let destination = self.parse_destination()?;
+        Ok(Statement::Collocate { node, source, window, ranked_by, destination })
+    }
+
+    // True if the current token is the plain word `word` (not a keyword).
+    fn at_word(&self, word: &str) -> bool {
+        matches!(self.current_token(), Some(Token::Identifier(w)) if w.eq_ignore_ascii_case(word))
+    }
+
+    // Columns are named like variables, and may share a name with a keyword (`count`).
+    fn parse_column_name(&mut self) -> Result<String, Diagnostic> {
+        match self.current_token() {
+            Some(Token::String(name)) => { let name = name.clone(); self.advance(); Ok(name) }
+            _ => match self.parse_identifier_expression() {
+                Ok(Expression::Identifier(name)) => Ok(name),
+                _ => Err(self.expected("a column name")),
+            },
+        }
+    }
+
+    fn parse_define_statement(&mut self) -> Result<Statement, Diagnostic> {
+        let define_span = self.current_span();
+        self.advance();
+        if self.current_token() == Some(&Token::Procedure) {
+            return self.parse_procedure_definition(define_span);
+        }
         let name = self.parse_identifier_expression()?;
         self.consume(Token::As)?;
         let value = self.parse_expression()?;
         Ok(Statement::Define { name, value })
     }
 
-    fn parse_arithmetic_statement(&mut self, op: ArithmeticOp) -> Result<Statement, String> {
+    // `set total to total + 1` changes a variable that already exists.
+    fn parse_set_statement(&mut self) -> Result<Statement, Diagnostic> {
+        self.advance();
+        // `set precision to 2` is a setting, not a variable called `precision`.
+        if matches!(self.current_token(), Some(Token::Identifier(name)) if name.eq_ignore_ascii_case("precision")) && self.peek_token() == Some(&Token::To) {
+            self.advance();
+            self.advance();
+            return Ok(Statement::SetPrecision(self.parse_expression()?));
+        }
+        let name = self.parse_identifier_expression()?;
+        self.consume(Token::To)?;
+        let value = self.parse_expression()?;
+        Ok(Statement::Assign { name, value })
+    }
+
+    // `assign total + 1 to total`
+    fn parse_assign_statement(&mut self) -> Result<Statement, Diagnostic> {
+        self.advance();
+        let value = self.parse_expression()?;
+        self.consume(Token::To)?;
+        let name = self.parse_identifier_expression()?;
+        Ok(Statement::Assign { name, value })
+    }
+
+    fn parse_arithmetic_statement(&mut self, op: ArithmeticOp) -> Result<Statement, Diagnostic> {
         self.advance();
         let (target, value) = match op {
+            // `then add 5` adds to the previous result.
             ArithmeticOp::Add | ArithmeticOp::Subtract => {
                 let val = self.parse_expression()?;
-                if self.current_token() == Some(&Token::To) || self.current_token() == Some(&Token::From) { self.advance(); } else { return Err("Expected 'to' or 'from'".to_string()); }
-                let tar = self.parse_expression()?;
+                let tar = if self.current_token() == Some(&Token::To) || self.current_token() == Some(&Token::From) { self.advance(); self.parse_expression()? } else { Self::implicit_result() };
                 (tar, val)
             },
             ArithmeticOp::Multiply | ArithmeticOp::Divide => {
-                let tar = self.parse_expression()?;
+                let tar = self.parse_source()?;
                 self.consume(Token::By)?;
                 let val = self.parse_expression()?;
                 (tar, val)
@@ -99,58 +696,371 @@ impl Parser {
     }
     
     // Other existing parsers
-    fn parse_use_statement(&mut self) -> Result<Statement, String> { self.advance(); let backend = match self.current_token() { Some(Token::Python) => Backend::Python, Some(Token::R) => Backend::R, _ => return Err("Expected 'python' or 'r' after 'use'".to_string()) }; self.advance(); Ok(Statement::Use(backend)) }
-    fn parse_summarize_statement(&mut self) -> Result<Statement, String> { self.advance(); let source = self.parse_expression()?; self.consume(Token::As)?; let destination = self.parse_identifier_expression()?; Ok(Statement::Summarize { source, destination }) }
-    fn parse_lemmatize_statement(&mut self) -> Result<Statement, String> { self.advance(); let source = self.parse_expression()?; self.consume(Token::As)?; let destination = self.parse_identifier_expression()?; Ok(Statement::Lemmatize { source, destination }) }
-    fn parse_filter_statement(&mut self) -> Result<Statement, String> { self.advance(); let source = self.parse_expression()?; let condition = match self.current_token() { Some(Token::Containing) => { self.advance(); FilterCondition::Containing(self.parse_expression()?) }, Some(Token::StartingWith) => { self.advance(); FilterCondition::StartingWith(self.parse_expression()?) }, Some(Token::EndingWith) => { self.advance(); FilterCondition::EndingWith(self.parse_expression()?) }, _ => return Err("Expected a filter condition".to_string()) }; self.consume(Token::As)?; let destination = self.parse_identifier_expression()?; Ok(Statement::Filter { source, condition, destination }) }
-    fn parse_save_statement(&mut self) -> Result<Statement, String> { self.advance(); let source = self.parse_expression()?; self.consume(Token::To)?; let destination = self.parse_expression()?; Ok(Statement::Save { source, destination }) }
-    fn parse_print_statement(&mut self) -> Result<Statement, String> { self.advance(); let expr = self.parse_expression()?; Ok(Statement::Print(expr)) }
-
-    // Expression parsing logic
-    fn parse_expression(&mut self) -> Result<Expression, String> {
+    fn parse_use_statement(&mut self) -> Result<Statement, Diagnostic> { self.advance(); let backend = match self.current_token() { Some(Token::Python) => Backend::Python, Some(Token::R) => Backend::R, _ => return Err(self.expected("a backend (`python` or `r`) after `use`")) }; self.advance(); Ok(Statement::Use(backend)) }
+    fn parse_summarize_statement(&mut self) -> Result<Statement, Diagnostic> { self.advance(); let source = self.parse_source()?; let destination = self.parse_destination()?; Ok(Statement::Summarize { source, destination }) }
+    fn parse_lemmatize_statement(&mut self) -> Result<Statement, Diagnostic> { self.advance(); let source = self.parse_source()?; let destination = self.parse_destination()?; Ok(Statement::Lemmatize { source, destination }) }
+    fn parse_filter_statement(&mut self) -> Result<Statement, Diagnostic> { self.advance(); let source = self.parse_source()?; let condition = match self.current_token() { Some(Token::Containing) => { self.advance(); FilterCondition::Containing(self.parse_expression()?) }, Some(Token::StartingWith) => { self.advance(); FilterCondition::StartingWith(self.parse_expression()?) }, Some(Token::EndingWith) => { self.advance(); FilterCondition::EndingWith(self.parse_expression()?) }, Some(Token::Where) => { self.advance(); FilterCondition::Where(self.parse_expression()?) }, _ if self.at_word("matching") => { self.advance(); if matches!(self.current_token(), Some(Token::Regex | Token::Pattern)) { self.advance(); } FilterCondition::Matching(self.parse_expression()?) }, _ => return Err(self.expected("a filter condition (`containing`, `starting_with`, `ending_with`, `matching` or `where`)")) }; let destination = self.parse_destination()?; Ok(Statement::Filter { source, condition, destination }) }
+    fn parse_save_statement(&mut self) -> Result<Statement, Diagnostic> { self.advance(); let source = self.parse_source()?; self.consume(Token::To)?; let destination = self.parse_expression()?; Ok(Statement::Save { source, destination }) }
+    fn parse_print_statement(&mut self) -> Result<Statement, Diagnostic> { self.advance(); let expr = self.parse_source()?; Ok(Statement::Print(expr)) }
+
+    // The value a command works on. When it is left out (`then tokenize`,
+    // `print`), the command works on `it`, the result of the previous command.
+    fn parse_source(&mut self) -> Result<Expression, Diagnostic> {
+        let omitted = matches!(self.current_token(), Some(Token::Then | Token::As | Token::To | Token::With | Token::By
+            | Token::Containing | Token::StartingWith | Token::EndingWith | Token::Where | Token::Ascending | Token::Descending
+            | Token::Newline | Token::Terminator | Token::Eof | Token::Otherwise | Token::End) | None);
+        if omitted || self.at_word("matching") { Ok(Self::implicit_result()) } else { self.parse_expression() }
+    }
+
+    // `as <name>`. Without it the result is only kept in `it`.
+    fn parse_destination(&mut self) -> Result<Expression, Diagnostic> {
+        if self.current_token() != Some(&Token::As) { return Ok(Self::implicit_result()); }
+        self.advance();
+        self.parse_identifier_expression()
+    }
+
+    fn implicit_result() -> Expression {
+        Expression::Identifier(IMPLICIT_RESULT.to_string())
+    }
+
+    // Expression parsing logic. From loosest to tightest binding:
+    //   or  ->  and  ->  not  ->  comparisons  ->  + -  ->  * /  ->  unary -  ->  values
+    fn parse_expression(&mut self) -> Result<Expression, Diagnostic> {
+        self.parse_or()
+    }
+
+    fn parse_or(&mut self) -> Result<Expression, Diagnostic> {
+        let mut left = self.parse_and()?;
+        while self.current_token() == Some(&Token::Or) {
+            self.advance();
+            let right = self.parse_and()?;
+            left = Expression::Logical { op: LogicalOp::Or, left: Box::new(left), right: Box::new(right) };
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expression, Diagnostic> {
+        let mut left = self.parse_not()?;
+        while self.current_token() == Some(&Token::And) {
+            self.advance();
+            let right = self.parse_not()?;
+            left = Expression::Logical { op: LogicalOp::And, left: Box::new(left), right: Box::new(right) };
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Expression, Diagnostic> {
+        if self.current_token() == Some(&Token::Not) {
+            self.advance();
+            return Ok(Expression::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_comparison()
+    }
+
+    // Symbols (`x >= 10`) and their English forms (`x is greater than or equal to 10`, `x is not 3`).
+    fn parse_comparison(&mut self) -> Result<Expression, Diagnostic> {
+        let left = self.spanned(Self::parse_additive)?;
+        let op = match self.current_token() {
+            Some(Token::Contains) => {
+                self.advance();
+                let needle = self.parse_additive()?;
+                return Ok(Expression::Contains { haystack: Box::new(left.node), needle: Box::new(needle) });
+            }
+            Some(Token::Is) => {
+                self.advance();
+                let negated = if self.current_token() == Some(&Token::Not) { self.advance(); true } else { false };
+                let op = self.parse_comparison_words().unwrap_or(ComparisonOp::Equal);
+                if negated { negate_comparison(op) } else { op }
+            }
+            Some(Token::Equal | Token::NotEqual | Token::Less | Token::LessEqual | Token::Greater | Token::GreaterEqual) => {
+                self.parse_comparison_words().expect("comparison token")
+            }
+            _ => return Ok(left.node),
+        };
+        let right = self.spanned(Self::parse_additive)?;
+        Ok(Expression::Comparison { op, left: Box::new(left), right: Box::new(right) })
+    }
+
+    // `greater than [or equal to]`, `less than [or equal to]`, `equal to` and the symbols.
+    fn parse_comparison_words(&mut self) -> Option<ComparisonOp> {
+        let op = match self.current_token()? {
+            Token::Equal => ComparisonOp::Equal,
+            Token::NotEqual => ComparisonOp::NotEqual,
+            Token::Less => ComparisonOp::Less,
+            Token::LessEqual => ComparisonOp::LessEqual,
+            Token::Greater => ComparisonOp::Greater,
+            Token::GreaterEqual => ComparisonOp::GreaterEqual,
+            _ => return None,
+        };
+        self.advance();
+        if op == ComparisonOp::Equal && self.current_token() == Some(&Token::To) { self.advance(); }
+        if matches!(op, ComparisonOp::Less | ComparisonOp::Greater) && self.current_token() == Some(&Token::Than) {
+            self.advance();
+            if self.current_token() == Some(&Token::Or) && self.peek_token() == Some(&Token::Equal) {
+                self.advance();
+                self.advance();
+                if self.current_token() == Some(&Token::To) { self.advance(); }
+                return Some(if op == ComparisonOp::Less { ComparisonOp::LessEqual } else { ComparisonOp::GreaterEqual });
+            }
+        }
+        Some(op)
+    }
+
+    fn parse_additive(&mut self) -> Result<Expression, Diagnostic> {
+        let mut left = self.spanned(Self::parse_multiplicative)?;
+        loop {
+            let op = match self.current_token() {
+                Some(Token::Plus) => ArithmeticOp::Add,
+                Some(Token::Minus) => ArithmeticOp::Subtract,
+                _ => return Ok(left.node),
+            };
+            self.advance();
+            let right = self.spanned(Self::parse_multiplicative)?;
+            let span = left.span.to(right.span);
+            left = Spanned::new(Expression::Arithmetic { op, left: Box::new(left), right: Box::new(right) }, span);
+        }
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expression, Diagnostic> {
+        let mut left = self.spanned(Self::parse_unary)?;
+        loop {
+            let op = match self.current_token() {
+                Some(Token::Times) => ArithmeticOp::Multiply,
+                Some(Token::Divided) => ArithmeticOp::Divide,
+                _ => return Ok(left.node),
+            };
+            self.advance();
+            // `x divided by y`: the `by` is optional so `x / y` works too.
+            if op == ArithmeticOp::Divide && self.current_token() == Some(&Token::By) { self.advance(); }
+            let right = self.spanned(Self::parse_unary)?;
+            let span = left.span.to(right.span);
+            left = Spanned::new(Expression::Arithmetic { op, left: Box::new(left), right: Box::new(right) }, span);
+        }
+    }
+
+    // Parses with `parse` and records the span of everything it consumed.
+    fn spanned(&mut self, parse: impl FnOnce(&mut Self) -> Result<Expression, Diagnostic>) -> Result<Spanned<Expression>, Diagnostic> {
+        let start = self.current_span();
+        let expression = parse(self)?;
+        Ok(Spanned::new(expression, start.to(self.previous_span())))
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression, Diagnostic> {
+        if self.current_token() == Some(&Token::Minus) {
+            self.advance();
+            return Ok(Expression::Negate(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, Diagnostic> {
         match self.current_token().cloned() {
             Some(Token::String(v)) => { self.advance(); Ok(Expression::StringLiteral(v)) },
             Some(Token::Number(v)) => { self.advance(); Ok(Expression::Number(v)) },
-            // For any other token, treat it as a potential identifier.
-            Some(_) => self.parse_identifier_expression(),
-            None => Err("Expected an expression, but found nothing.".to_string()),
+            Some(Token::Float(v)) => { self.advance(); Ok(Expression::Float(v)) },
+            Some(Token::True) => { self.advance(); Ok(Expression::Boolean(true)) },
+            Some(Token::False) => { self.advance(); Ok(Expression::Boolean(false)) },
+            // `(a + b)` groups; `(a, b)` is a tuple.
+            Some(Token::LeftParen) => {
+                let opening = self.current_span();
+                self.advance();
+                if self.current_token() == Some(&Token::RightParen) { self.advance(); return Ok(Expression::Tuple(Vec::new())); }
+                let first = self.parse_expression()?;
+                if self.current_token() != Some(&Token::Comma) {
+                    self.consume_closing(Token::RightParen, opening)?;
+                    return Ok(first);
+                }
+                self.advance();
+                let mut items = vec![first];
+                items.extend(self.parse_comma_separated(Token::RightParen, opening, Self::parse_expression)?);
+                Ok(Expression::Tuple(items))
+            }
+            Some(Token::LeftBracket) => {
+                let opening = self.current_span();
+                self.advance();
+                Ok(Expression::List(self.parse_comma_separated(Token::RightBracket, opening, Self::parse_expression)?))
+            }
+            Some(Token::LeftBrace) => self.parse_map_literal(),
+            Some(Token::Top | Token::Bottom) => {
+                let from_bottom = self.current_token() == Some(&Token::Bottom);
+                self.advance();
+                let count = self.parse_additive()?;
+                self.consume(Token::Of)?;
+                let collection = self.parse_unary()?;
+                Ok(Expression::Top { from_bottom, count: Box::new(count), collection: Box::new(collection) })
+            }
+            // `the label of entity`: with `the`, any word can name a field, even `keys`.
+            Some(Token::Identifier(word)) if word.eq_ignore_ascii_case("the") && self.peek_token() != Some(&Token::Of) => {
+                self.advance();
+                let Expression::Identifier(name) = self.parse_identifier_expression()? else { unreachable!("parse_identifier_expression only returns identifiers") };
+                self.consume(Token::Of)?;
+                let record = self.parse_unary()?;
+                Ok(Expression::Field { name, record: Box::new(record) })
+            }
+            Some(Token::Item) => {
+                self.advance();
+                let index = self.parse_additive()?;
+                self.consume(Token::Of)?;
+                let collection = self.parse_unary()?;
+                Ok(Expression::Item { index: Box::new(index), collection: Box::new(collection) })
+            }
+            Some(Token::First | Token::Last) => {
+                let end = if self.current_token() == Some(&Token::First) { SliceEnd::First } else { SliceEnd::Last };
+                self.advance();
+                let count = if self.current_token() == Some(&Token::Of) { None } else { Some(Box::new(self.parse_additive()?)) };
+                self.consume(Token::Of)?;
+                let collection = self.parse_unary()?;
+                Ok(Expression::Slice { end, count, collection: Box::new(collection) })
+            }
+            Some(Token::Newline | Token::Terminator | Token::Eof) | None => Err(self.expected("a value")),
+            Some(t) if Self::is_operator(&t) => Err(self.expected("a value")),
+            // For any other token, treat it as a potential identifier, or a field
+            // when followed by `of` (`label of entity`, `keys of record`).
+            Some(_) => {
+                let identifier = self.parse_identifier_expression()?;
+                if self.current_token() != Some(&Token::Of) { return Ok(identifier); }
+                self.advance();
+                let record = Box::new(self.parse_unary()?);
+                let Expression::Identifier(name) = identifier else { unreachable!("parse_identifier_expression only returns identifiers") };
+                Ok(match name.to_lowercase().as_str() {
+                    "keys" => Expression::Keys(record),
+                    "values" => Expression::Values(record),
+                    "table" => Expression::ToTable(record),
+                    _ => Expression::Field { name, record },
+                })
+            }
+        }
+    }
+
+    // `{ name: "Reed", "label": "PERSON" }`. Keys are names or text.
+    fn parse_map_literal(&mut self) -> Result<Expression, Diagnostic> {
+        let opening = self.current_span();
+        self.advance(); // consume '{'
+        let entries = self.parse_comma_separated(Token::RightBrace, opening, |parser| {
+            let key_span = parser.current_span();
+            let key = match parser.current_token().cloned() {
+                Some(Token::String(key)) => { parser.advance(); key }
+                _ => match parser.parse_identifier_expression() {
+                    Ok(Expression::Identifier(key)) => key,
+                    _ => return Err(parser.expected("a key (a name or some text)")),
+                },
+            };
+            parser.consume(Token::Colon)?;
+            let value = parser.parse_expression()?;
+            Ok((key, key_span, value))
+        })?;
+        let mut fields: Vec<(String, Expression)> = Vec::new();
+        for (key, key_span, value) in entries {
+            if fields.iter().any(|(existing, _)| *existing == key) {
+                return Err(Diagnostic::new(format!("The key `{}` appears twice in this record.", key), key_span));
+            }
+            fields.push((key, value));
+        }
+        Ok(Expression::Map(fields))
+    }
+
+    // `item, item, ...` up to and including `closing`. A trailing comma is fine.
+    fn parse_comma_separated<T>(&mut self, closing: Token, opening: Span, mut parse_item: impl FnMut(&mut Self) -> Result<T, Diagnostic>) -> Result<Vec<T>, Diagnostic> {
+        let mut items = Vec::new();
+        while self.current_token() != Some(&closing) {
+            items.push(parse_item(self)?);
+            if self.current_token() != Some(&Token::Comma) { break; }
+            self.advance();
+        }
+        self.consume_closing(closing, opening)?;
+        Ok(items)
+    }
+
+    // Line breaks are ignored inside brackets, so a missing closing bracket is
+    // usually only noticed at the end of the script: point at the opening one.
+    fn consume_closing(&mut self, closing: Token, opening: Span) -> Result<(), Diagnostic> {
+        match self.current_token() {
+            Some(token) if *token == closing => { self.advance(); Ok(()) }
+            Some(Token::Eof) | None => Err(Diagnostic::new("This bracket is never closed.", opening)
+                .with_hint(format!("add `{}` after the last item", closing.spelling()))),
+            _ => Err(self.expected(&format!("`,` or `{}`", closing.spelling()))
+                .with_hint(format!("the bracket opened on line {}, column {} is still open here", opening.line, opening.column))),
         }
     }
 
+    fn is_operator(token: &Token) -> bool {
+        matches!(token, Token::Plus | Token::Minus | Token::Times | Token::Divided | Token::Equal | Token::NotEqual
+            | Token::Less | Token::LessEqual | Token::Greater | Token::GreaterEqual | Token::And | Token::Or
+            | Token::Not | Token::Is | Token::Than | Token::Contains | Token::True | Token::False | Token::LeftParen | Token::RightParen
+            | Token::Comma | Token::LeftBracket | Token::RightBracket | Token::LeftBrace | Token::RightBrace | Token::Colon)
+    }
+
     // This function now correctly handles keywords by turning them into identifier strings.
-    fn parse_identifier_expression(&mut self) -> Result<Expression, String> {
+    fn parse_identifier_expression(&mut self) -> Result<Expression, Diagnostic> {
         match self.current_token().cloned() {
             Some(Token::Identifier(name)) => {
                 self.advance();
                 Ok(Expression::Identifier(name))
             }
+            // Literals, stray characters and the end of input can never be names.
+            Some(Token::Eof | Token::Newline | Token::Terminator | Token::Illegal(_) | Token::String(_) | Token::Number(_) | Token::Float(_)) | None => {
+                Err(self.expected("a variable name"))
+            }
+            Some(tok) if Self::is_operator(&tok) => Err(self.expected("a variable name")),
             Some(tok) => {
                 let identifier_string = format!("{:?}", tok).to_lowercase();
                 self.advance();
                 Ok(Expression::Identifier(identifier_string))
             }
-            None => Err("Expected a variable name, but found nothing.".to_string())
         }
     }
 
     // This function specifically looks for unit keywords.
-    fn parse_unit_expression(&mut self) -> Result<Expression, String> {
+    fn parse_unit_expression(&mut self) -> Result<Expression, Diagnostic> {
         match self.current_token() {
             Some(tok) if self.is_unit_token(tok) => {
                 let unit_token = tok.clone();
                 self.advance();
                 Ok(Expression::Unit(unit_token))
             },
-            _ => Err(format!("Expected a unit (like words, ner, etc), but found {:?}", self.current_token())),
+            _ => Err(self.expected("a unit (like `words`, `lines` or `ner`)")),
         }
     }
     
     fn is_unit_token(&self, token: &Token) -> bool {
-        matches!(token, Token::Words | Token::Sentences | Token::Lines | Token::Paragraphs | Token::Characters | Token::Tokens | Token::Types | Token::Uniques | Token::Length | Token::POS | Token::NER | Token::Entities)
+        matches!(token, Token::Words | Token::Sentences | Token::Lines | Token::Paragraphs | Token::Characters | Token::Tokens | Token::Types | Token::Uniques | Token::Length | Token::POS | Token::NER | Token::Entities
+            | Token::Bigrams | Token::Trigrams)
     }
     
-    fn current_token(&self) -> Option<&Token> { self.tokens.get(self.position) }
+    fn current_token(&self) -> Option<&Token> { self.tokens.get(self.position).map(|t| &t.node) }
+    fn peek_token(&self) -> Option<&Token> { self.tokens.get(self.position + 1).map(|t| &t.node) }
+    fn current_span(&self) -> Span { self.tokens.get(self.position).or(self.tokens.last()).map_or(Span::default(), |t| t.span) }
+    fn previous_span(&self) -> Span { self.tokens.get(self.position.saturating_sub(1)).map_or(Span::default(), |t| t.span) }
     fn advance(&mut self) { if !self.is_at_end() { self.position += 1; } }
     fn is_at_end(&self) -> bool { self.position >= self.tokens.len() || self.current_token() == Some(&Token::Eof) }
-    fn consume(&mut self, expected: Token) -> Result<(), String> { if let Some(t) = self.current_token() { if std::mem::discriminant(t) == std::mem::discriminant(&expected) { self.advance(); return Ok(()); } } Err(format!("Expected {:?}, found {:?}", expected, self.current_token())) }
-}
\ No newline at end of file
+    fn consume(&mut self, expected: Token) -> Result<(), Diagnostic> {
+        if let Some(t) = self.current_token() { if std::mem::discriminant(t) == std::mem::discriminant(&expected) { self.advance(); return Ok(()); } }
+        let error = self.expected(&format!("`{}`", expected.spelling()));
+        // A misspelled keyword is by far the most common cause, so check for one.
+        Err(match self.current_token() {
+            Some(Token::Identifier(name)) if suggest(name, [expected.spelling().as_str()]).is_some() => {
+                error.with_hint(format!("did you mean `{}`?", expected.spelling()))
+            }
+            _ => error,
+        })
+    }
+
+    // Error helpers: all parse errors point at the current token.
+    fn error(&self, message: impl Into<String>) -> Diagnostic { Diagnostic::new(message, self.current_span()) }
+    fn expected(&self, what: &str) -> Diagnostic {
+        let found = self.current_token().map_or("the end of the script".to_string(), |t| t.describe());
+        self.error(format!("Expected {} here, but found {}.", what, found))
+    }
+}
+
+// `is not greater than` means `is less than or equal to`, and so on.
+fn negate_comparison(op: ComparisonOp) -> ComparisonOp {
+    match op {
+        ComparisonOp::Equal => ComparisonOp::NotEqual,
+        ComparisonOp::NotEqual => ComparisonOp::Equal,
+        ComparisonOp::Less => ComparisonOp::GreaterEqual,
+        ComparisonOp::LessEqual => ComparisonOp::Greater,
+        ComparisonOp::Greater => ComparisonOp::LessEqual,
+        ComparisonOp::GreaterEqual => ComparisonOp::Less,
+    }
+}
@@ -0,0 +1,139 @@
+// src/runtime/cleaning.rs
+
+// Text cleaning, done natively so it needs no backend. Every step works on a
+// text or on each item of a list. On a text it edits the text in place; on a
+// list of words it also drops the items that end up empty, so
+// `remove punctuation from tokens` leaves no "," tokens behind.
+
+use std::collections::HashSet;
+
+use regex::Regex;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::ast::CleaningStep;
+use crate::runtime::stopwords::is_stopword;
+use crate::runtime::TaleaValue;
+
+// What `clean` does when no steps are given.
+pub const DEFAULT_STEPS: &[CleaningStep] = &[CleaningStep::Normalize, CleaningStep::Lowercase, CleaningStep::Punctuation, CleaningStep::Whitespace];
+
+// The order `clean` applies its steps in, whatever order they are listed in:
+// punctuation goes before stopwords so that "The," is still recognised.
+const STEP_ORDER: &[CleaningStep] = &[
+    CleaningStep::Normalize, CleaningStep::Lowercase, CleaningStep::Punctuation,
+    CleaningStep::Numbers, CleaningStep::Stopwords, CleaningStep::Whitespace,
+];
+
+// Whole numbers, decimals and numbers with separators: 12, 3.5, 1,000, 1:30.
+const NUMBER_PATTERN: &str = r"\p{N}+(?:[.,:]\p{N}+)*";
+
+pub fn change_case(value: &TaleaValue, upper: bool) -> Result<TaleaValue, String> {
+    let change = |text: &str| if upper { text.to_uppercase() } else { text.to_lowercase() };
+    map_texts(value, if upper { "uppercase" } else { "lowercase" }, &change, &|word| Some(change(word)))
+}
+
+// Unicode normalization (NFC, or NFKC with `compatibility`, which also turns
+// ligatures like "ﬁ" and full-width letters into plain ones), then curly
+// quotes and dashes folded to ' " and -, soft hyphens and zero-width
+// characters dropped, and runs of spaces and tabs made single (and dropped at
+// the ends of lines). Line breaks are kept.
+pub fn normalize(value: &TaleaValue, compatibility: bool) -> Result<TaleaValue, String> {
+    let normalize_text = |text: &str| normalize_text(text, compatibility);
+    map_texts(value, "normalize", &normalize_text, &|word| Some(normalize_text(word)).filter(|word| !word.is_empty()))
+}
+
+// One cleaning step; `remove ... from` runs the last four on their own.
+pub fn run_step(value: &TaleaValue, what: CleaningStep, stopwords: &HashSet<String>) -> Result<TaleaValue, String> {
+    match what {
+        CleaningStep::Normalize => normalize(value, false),
+        CleaningStep::Lowercase => change_case(value, false),
+        CleaningStep::Punctuation => {
+            map_texts(value, "remove punctuation", &strip_punctuation, &|word| Some(strip_punctuation(word)).filter(|word| !word.is_empty()))
+        }
+        CleaningStep::Numbers => {
+            let number = Regex::new(NUMBER_PATTERN).expect("the number pattern is valid");
+            let whole_number = Regex::new(&format!("^{}$", NUMBER_PATTERN)).expect("the number pattern is valid");
+            let value = drop_number_values(value);
+            map_texts(&value, "remove numbers", &|text| number.replace_all(text, "").into_owned(), &|word| {
+                (!whole_number.is_match(word.trim_matches(|c: char| !c.is_alphanumeric()))).then(|| word.to_string())
+            })
+        }
+        CleaningStep::Stopwords => {
+            let is_stopword = |word: &str| is_stopword(stopwords, word);
+            map_texts(value, "remove stopwords",
+                &|text| text.split_whitespace().filter(|word| !is_stopword(word)).collect::<Vec<_>>().join(" "),
+                &|word| (!is_stopword(word)).then(|| word.to_string()))
+        }
+        CleaningStep::Whitespace => {
+            let collapse = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+            map_texts(value, "remove whitespace", &collapse, &|word| Some(collapse(word)).filter(|word| !word.is_empty()))
+        }
+    }
+}
+
+// Runs the given steps (or the default ones) in `STEP_ORDER`.
+pub fn clean(value: &TaleaValue, steps: &[CleaningStep], stopwords: &HashSet<String>) -> Result<TaleaValue, String> {
+    let steps = if steps.is_empty() { DEFAULT_STEPS } else { steps };
+    let mut value = value.clone();
+    for step in STEP_ORDER.iter().filter(|step| steps.contains(step)) {
+        value = run_step(&value, *step, stopwords)?;
+    }
+    Ok(value)
+}
+
+// `on_text` edits a whole text; `on_word` edits one list item, returning
+// `None` to drop it. Items that aren't text are kept as they are.
+fn map_texts(value: &TaleaValue, what: &str, on_text: &dyn Fn(&str) -> String, on_word: &dyn Fn(&str) -> Option<String>) -> Result<TaleaValue, String> {
+    match value {
+        TaleaValue::String(text) => Ok(TaleaValue::String(on_text(text))),
+        TaleaValue::List(items) | TaleaValue::Tuple(items) => {
+            let mut kept = Vec::with_capacity(items.len());
+            for item in items {
+                match item {
+                    TaleaValue::String(word) => kept.extend(on_word(word).map(TaleaValue::String)),
+                    TaleaValue::List(_) | TaleaValue::Tuple(_) => kept.push(map_texts(item, what, on_text, on_word)?),
+                    other => kept.push(other.clone()),
+                }
+            }
+            Ok(if matches!(value, TaleaValue::Tuple(_)) { TaleaValue::Tuple(kept) } else { TaleaValue::List(kept) })
+        }
+        other => Err(format!("`{}` works on text and lists of text, but got {}.", what, other.type_name())),
+    }
+}
+
+fn drop_number_values(value: &TaleaValue) -> TaleaValue {
+    match value {
+        TaleaValue::List(items) => TaleaValue::List(items.iter().filter(|item| !matches!(item, TaleaValue::Number(_) | TaleaValue::Float(_))).map(drop_number_values).collect()),
+        other => other.clone(),
+    }
+}
+
+fn normalize_text(text: &str, compatibility: bool) -> String {
+    let composed: String = if compatibility { text.nfkc().collect() } else { text.nfc().collect() };
+    let folded = composed.replace("\r\n", "\n").chars().filter_map(|c| match c {
+        '\u{00AD}' | '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{FEFF}' => None,
+        '‘' | '’' | '‚' | '‛' | '′' => Some('\''),
+        '“' | '”' | '„' | '‟' | '″' => Some('"'),
+        '‐' | '‑' | '‒' | '–' | '—' | '―' | '−' => Some('-'),
+        c => Some(c),
+    }).collect::<String>();
+    folded.split('\n')
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Drops punctuation and other symbols, but keeps letters (with their accents),
+// digits and whitespace, apostrophes and hyphens inside a word ("don't",
+// "well-known") and separators inside a number ("1,000", "3.5").
+fn strip_punctuation(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let is_word_char = |c: char| c.is_alphanumeric() || is_combining_mark(c);
+    let between = |i: usize, test: &dyn Fn(char) -> bool| i > 0 && test(chars[i - 1]) && chars.get(i + 1).is_some_and(|&next| test(next));
+    chars.iter().enumerate().filter(|&(i, &c)| {
+        is_word_char(c) || c.is_whitespace()
+            || matches!(c, '\'' | '’' | '-' | '‐') && between(i, &is_word_char)
+            || matches!(c, '.' | ',') && between(i, &|c: char| c.is_numeric())
+    }).map(|(_, &c)| c).collect()
+}
@@ -0,0 +1,252 @@
+// src/runtime/corpus.rs
+
+// Corpus-linguistics measures. Each takes plain words and returns a `Table`
+// that can be printed, filtered, sorted and saved like any other.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::ContextSide;
+use crate::runtime::stopwords::is_stopword;
+use crate::runtime::table::{CellOrder, Table};
+use crate::runtime::TaleaValue;
+
+// How many words on each side of a concordance keyword decide the order of
+// `sorted by left` / `sorted by right`.
+const CONTEXT_SORT_WORDS: usize = 5;
+
+// The words of a text (split on whitespace) or of a list (its items, e.g. the
+// output of `tokenize`).
+pub fn words_of(value: &TaleaValue) -> Result<Vec<String>, String> {
+    match value {
+        TaleaValue::String(text) => Ok(text.split_whitespace().map(str::to_string).collect()),
+        TaleaValue::List(items) | TaleaValue::Tuple(items) => items.iter().map(|item| match item {
+            TaleaValue::String(word) => Ok(word.clone()),
+            TaleaValue::Number(_) | TaleaValue::Float(_) | TaleaValue::Boolean(_) => Ok(item.display(None)),
+            other => Err(format!("Expected a list of words, but it contains {}.", other.type_name())),
+        }).collect(),
+        other => Err(format!("Expected some text or a list of words, but got {}.", other.type_name())),
+    }
+}
+
+// The named documents in a value: a record of texts (or word lists) is one
+// document per field; anything else is a single document called `name`.
+pub fn documents_of(name: &str, value: &TaleaValue) -> Result<Vec<(String, Vec<String>)>, String> {
+    match value {
+        TaleaValue::Map(fields) => fields.iter().map(|(field, text)| Ok((field.clone(), words_of(text)?))).collect(),
+        other => Ok(vec![(name.to_string(), words_of(other)?)]),
+    }
+}
+
+// A word without punctuation at either end ("said:" is "said"), as it
+// appears in n-grams.
+fn bare_word(word: &str) -> &str {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+}
+
+// True if a sentence ends with this word: it ends in `.`, `!`, `?` or `…`,
+// perhaps followed by a closing quote or bracket.
+fn ends_sentence(word: &str) -> bool {
+    word.trim_end_matches(['"', '\'', ')', ']', '»', '”', '’']).ends_with(['.', '!', '?', '…'])
+}
+
+// The runs of `size` consecutive words, joined by spaces. Punctuation
+// attached to a word is dropped, and n-grams never run across the end of a
+// sentence. With `stopwords`, n-grams starting or ending with one are left
+// out, which keeps phrases like "freedom of speech" but not "of the".
+pub fn ngrams_of(words: &[String], size: usize, stopwords: Option<&HashSet<String>>) -> Vec<String> {
+    let is_stopword = |word: &str| stopwords.is_some_and(|list| is_stopword(list, word));
+    let mut ngrams = Vec::new();
+    let mut sentence: Vec<&str> = Vec::new();
+    for (i, word) in words.iter().enumerate() {
+        let bare = bare_word(word);
+        if !bare.is_empty() { sentence.push(bare); }
+        if !ends_sentence(word) && i + 1 < words.len() { continue; }
+        for ngram in sentence.windows(size) {
+            if is_stopword(ngram[0]) || is_stopword(ngram[size - 1]) { continue; }
+            ngrams.push(ngram.join(" "));
+        }
+        sentence.clear();
+    }
+    ngrams
+}
+
+// A word as it is matched: lower case, without punctuation at either end.
+fn match_form(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase()
+}
+
+// A keyword-in-context view: one row per occurrence of `keyword` (one or
+// more words), with
+//   document  where it occurs
+//   position  the number of its first word in that document, counting from 1
+//   left      up to `width` characters of text before it
+//   keyword   the words as they are written in the text
+//   right     up to `width` characters of text after it
+// Words match whatever their case and any punctuation attached to them, so
+// "love" also finds "Love," and "love.".
+pub fn concordance_table(documents: &[(String, Vec<String>)], keyword: &str, width: usize, sorted_by: Option<ContextSide>) -> Result<Table, String> {
+    let pattern: Vec<String> = keyword.split_whitespace().map(match_form).collect();
+    if pattern.is_empty() || pattern.iter().any(String::is_empty) {
+        return Err("`concordance` needs a keyword with at least one letter or digit in each word.".to_string());
+    }
+    let mut lines: Vec<(Vec<TaleaValue>, Vec<TaleaValue>)> = Vec::new();
+    for (document, words) in documents {
+        let forms: Vec<String> = words.iter().map(|word| match_form(word)).collect();
+        for start in 0..words.len().saturating_sub(pattern.len() - 1) {
+            if forms[start..start + pattern.len()] != pattern[..] { continue; }
+            let end = start + pattern.len();
+            let sort_key = match sorted_by {
+                Some(ContextSide::Left) => forms[..start].iter().rev().take(CONTEXT_SORT_WORDS).cloned().map(TaleaValue::String).collect(),
+                Some(ContextSide::Right) => forms[end..].iter().take(CONTEXT_SORT_WORDS).cloned().map(TaleaValue::String).collect(),
+                None => Vec::new(),
+            };
+            lines.push((vec![
+                TaleaValue::String(document.clone()),
+                TaleaValue::Number(start as i64 + 1),
+                TaleaValue::String(left_context(&words[..start], width)),
+                TaleaValue::String(words[start..end].join(" ")),
+                TaleaValue::String(right_context(&words[end..], width)),
+            ], sort_key));
+        }
+    }
+    if sorted_by.is_some() {
+        // Word by word outwards from the keyword; a shorter context (at the
+        // edge of a document) comes before a longer one that starts the same.
+        let mut order = CellOrder::default();
+        lines.sort_by(|(_, a), (_, b)| {
+            a.iter().zip(b).map(|(x, y)| order.compare(x, y)).find(|ordering| ordering.is_ne()).unwrap_or_else(|| a.len().cmp(&b.len()))
+        });
+    }
+    let names = ["document", "position", "left", "keyword", "right"].map(String::from).to_vec();
+    Ok(Table::new(names, lines.into_iter().map(|(row, _)| row).collect())?.flush_right("left"))
+}
+
+// The last `width` characters of the words before a keyword.
+fn left_context(words: &[String], width: usize) -> String {
+    let mut taken = 0;
+    let mut length = 0;
+    while taken < words.len() && length < width {
+        length += words[words.len() - taken - 1].chars().count() + 1;
+        taken += 1;
+    }
+    let text = words[words.len() - taken..].join(" ");
+    let extra = text.chars().count().saturating_sub(width);
+    text.chars().skip(extra).collect()
+}
+
+// The first `width` characters of the words after a keyword.
+fn right_context(words: &[String], width: usize) -> String {
+    let mut taken = 0;
+    let mut length = 0;
+    while taken < words.len() && length < width {
+        length += words[taken].chars().count() + 1;
+        taken += 1;
+    }
+    words[..taken].join(" ").chars().take(width).collect()
+}
+
+// The association measures `collocate` can rank by.
+pub const ASSOCIATION_MEASURES: &[&str] = &["mi", "t_score", "log_likelihood", "log_dice"];
+
+// Collocates of `node`: the words occurring within `window` words either side
+// of it (never across documents), one row per word, with
+//   collocate       the word (lower case, without attached punctuation)
+//   observed        how often it occurs near the node
+//   expected        how often it would by chance: window size × word freq / corpus size
+//   frequency       how often it occurs in the whole corpus
+//   mi              mutual information, log2(observed / expected)
+//   t_score         (observed − expected) / √observed
+//   log_likelihood  Dunning's G² over the 2×2 table of window and corpus counts
+//   log_dice        14 + log2(2 × co-occurrences / (node freq + word freq)), at most 14
+// ranked by `measure`, highest first.
+//
+// The window is the set of positions near some occurrence of the node, so a
+// word between two nearby hits is counted once, windows are cut short at the
+// edges of a document, and the node itself is never in it. Its size is the
+// number of such positions. For log-Dice, the co-occurrences of the node and a
+// word are its observed count, but never more than the node's own frequency.
+pub fn collocation_table(documents: &[(String, Vec<String>)], node: &str, window: usize, measure: &str) -> Result<Table, String> {
+    let node = match_form(node);
+    if node.is_empty() || node.contains(char::is_whitespace) {
+        return Err("`collocate` needs a single word to find the collocates of.".to_string());
+    }
+    if window == 0 {
+        return Err("The collocation `window` must be at least 1 word.".to_string());
+    }
+    let documents: Vec<Vec<String>> = documents.iter()
+        .map(|(_, words)| words.iter().map(|word| match_form(word)).filter(|form| !form.is_empty()).collect())
+        .collect();
+    let corpus_size = documents.iter().map(Vec::len).sum::<usize>() as f64;
+    let mut frequencies: HashMap<&str, f64> = HashMap::new();
+    let mut observed: HashMap<&str, f64> = HashMap::new();
+    let mut window_words = 0.0;
+    for words in &documents {
+        let mut near_node = vec![false; words.len()];
+        for (i, word) in words.iter().enumerate() {
+            *frequencies.entry(word).or_insert(0.0) += 1.0;
+            if *word != node { continue; }
+            let span = i.saturating_sub(window)..(i + window + 1).min(words.len());
+            for j in span { near_node[j] = true; }
+        }
+        for (word, _) in words.iter().zip(near_node).filter(|(word, near)| *near && **word != node) {
+            *observed.entry(word).or_insert(0.0) += 1.0;
+            window_words += 1.0;
+        }
+    }
+    let node_frequency = frequencies.get(node.as_str()).copied().unwrap_or(0.0);
+
+    let mut collocates: Vec<(&str, f64)> = observed.into_iter().collect();
+    collocates.sort_by(|a, b| a.0.cmp(b.0));
+    let rows = collocates.into_iter().map(|(word, o11)| {
+        let frequency = frequencies[word];
+        let expected = window_words * frequency / corpus_size;
+        let cells = [
+            (o11, expected),
+            (window_words - o11, window_words * (corpus_size - frequency) / corpus_size),
+            (frequency - o11, (corpus_size - window_words) * frequency / corpus_size),
+            (corpus_size - window_words - frequency + o11, (corpus_size - window_words) * (corpus_size - frequency) / corpus_size),
+        ];
+        let log_likelihood = 2.0 * cells.iter()
+            .filter(|(o, e)| *o > 0.0 && *e > 0.0)
+            .map(|(o, e)| o * (o / e).ln())
+            .sum::<f64>();
+        vec![
+            TaleaValue::String(word.to_string()),
+            TaleaValue::Number(o11 as i64),
+            TaleaValue::Float(expected),
+            TaleaValue::Number(frequency as i64),
+            TaleaValue::Float((o11 / expected).log2()),
+            TaleaValue::Float((o11 - expected) / o11.sqrt()),
+            TaleaValue::Float(log_likelihood),
+            TaleaValue::Float(14.0 + (2.0 * o11.min(node_frequency) / (node_frequency + frequency)).log2()),
+        ]
+    }).collect();
+    let names = ["collocate", "observed", "expected", "frequency", "mi", "t_score", "log_likelihood", "log_dice"].map(String::from).to_vec();
+    Table::new(names, rows)?.ranked_by(measure, true)
+}
+
+// A frequency list: one row per distinct item, most frequent first, with
+//   rank         1 for the most frequent; tied items share a rank (1, 2, 2, 4)
+//   <column>     the item itself
+//   count        how often it occurs
+//   per_million  its relative frequency, normalised to a corpus of a million items
+pub fn frequency_table(items: &[String], column: &str, ignore_case: bool) -> Table {
+    let mut counts: HashMap<String, i64> = HashMap::new();
+    for item in items {
+        let key = if ignore_case { item.to_lowercase() } else { item.clone() };
+        *counts.entry(key).or_insert(0) += 1;
+    }
+    let total = items.len() as f64;
+    let mut counts: Vec<(String, i64)> = counts.into_iter().collect();
+    // Alphabetical first, so tied items stay in that order once ranked by count
+    // and the table is the same on every run.
+    counts.sort();
+    let rows = counts.into_iter().map(|(item, count)| vec![
+        TaleaValue::String(item),
+        TaleaValue::Number(count),
+        TaleaValue::Float(count as f64 / total * 1_000_000.0),
+    ]).collect();
+    Table::new(vec![column.to_string(), "count".to_string(), "per_million".to_string()], rows)
+        .and_then(|table| table.ranked_by("count", true))
+        .expect("the table has a count column and three cells per row")
+}
@@ -1,5 +1,6 @@
 use std::collections::HashSet;
 use std::fs;
+use std::rc::Rc;
 use shellexpand;
 
 // FFI Imports
@@ -9,13 +10,41 @@ use extendr_api::prelude as r_prelude;
 // FIX: We are no longer using the jni crate directly in this simplified version.
 
 // AST and Runtime Imports
-use crate::ast::{ArithmeticOp, Backend, Expression, FilterCondition, Statement};
-use crate::runtime::{Environment, TaleaValue};
+use crate::ast::{IMPLICIT_RESULT, ArithmeticOp, Backend, ComparisonOp, ContextSide, Expression, FilterCondition, LogicalOp, SliceEnd, SortKey, Statement};
+use crate::diagnostics::{suggest, Diagnostic, Span, Spanned};
+use crate::runtime::corpus::{collocation_table, concordance_table, documents_of, frequency_table, ngrams_of, words_of, ASSOCIATION_MEASURES};
+use crate::runtime::{cleaning, patterns, stopwords};
+use crate::runtime::table::{CellOrder, Table};
+use crate::runtime::{Environment, Procedure, TaleaValue};
 use crate::lexer::Token;
 
+// The most decimal places `set precision to ...` accepts.
+const MAX_PRECISION: usize = 15;
+// Another name for `it`, the result of the most recent command.
+const RESULT_ALIAS: &str = "result";
+// How deeply procedures may call each other before we assume runaway recursion.
+const MAX_CALL_DEPTH: usize = 200;
+// Characters of context on each side of a concordance keyword, unless `width` says otherwise.
+const DEFAULT_CONTEXT_WIDTH: usize = 40;
+// Words either side of the node that count as its neighbours, unless `window` says otherwise.
+const DEFAULT_COLLOCATION_WINDOW: usize = 5;
+
+// What the caller should do once `execute` has run a batch of statements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlFlow {
+    Continue,
+    Exit, // The script asked to stop with `exit`
+}
+
 pub struct Interpreter {
     environment: Environment,
     active_backends: HashSet<Backend>,
+    float_precision: Option<usize>, // `None` shows floats in their shortest form
+    call_depth: usize,              // Number of procedure calls currently running
+    returning: Option<TaleaValue>,  // Set by `return` until the procedure call picks it up
+    exiting: bool,                  // Set by `exit` until `execute` hands it to the caller
+    error_span: Option<Span>,       // The part of a failing statement an expression error points at
+    stopwords: HashSet<String>,     // The project's stopwords: English unless `load stopwords` replaced them
 }
 
 impl Interpreter {
@@ -23,33 +52,214 @@ impl Interpreter {
         Interpreter {
             environment: Environment::new(),
             active_backends: HashSet::new(),
+            float_precision: None,
+            call_depth: 0,
+            returning: None,
+            exiting: false,
+            error_span: None,
+            stopwords: stopwords::set_of(stopwords::ENGLISH),
         }
     }
 
     // FIX: Explicitly use `std::result::Result` to avoid name collision with extendr's Result type.
     // This fix is applied to all function signatures in this file.
-    pub fn execute(&mut self, statements: Vec<Statement>) -> std::result::Result<(), String> {
+    // Runtime errors are reported against the span of the statement that failed.
+    // Exiting is left to the caller, so a host embedding Talea keeps running.
+    pub fn execute(&mut self, statements: Vec<Spanned<Statement>>) -> std::result::Result<ControlFlow, Diagnostic> {
+        let outcome = self.execute_block(&statements);
+        let exiting = std::mem::take(&mut self.exiting);
+        outcome?;
+        Ok(if exiting { ControlFlow::Exit } else { ControlFlow::Continue })
+    }
+
+    // The value of a variable visible from where execution stopped.
+    pub fn variable(&self, name: &str) -> Option<TaleaValue> {
+        self.lookup(name)
+    }
+
+    // `result` reads `it`, unless the script has a `result` variable of its own.
+    fn lookup(&self, name: &str) -> Option<TaleaValue> {
+        self.environment.get(name).or_else(|| if name == RESULT_ALIAS { self.environment.get(IMPLICIT_RESULT) } else { None })
+    }
+
+    fn execute_block(&mut self, statements: &[Spanned<Statement>]) -> std::result::Result<(), Diagnostic> {
         // FIX: Iterate by reference to fix the mismatched types error.
-        for statement in &statements {
+        for statement in statements {
             self.execute_statement(statement)?;
+            // A `return` skips the rest of every block up to its procedure,
+            // and `exit` skips everything.
+            if self.interrupted() { break; }
         }
         Ok(())
     }
 
-    fn execute_statement(&mut self, statement: &Statement) -> std::result::Result<(), String> {
-        match statement {
+    fn execute_statement(&mut self, statement: &Spanned<Statement>) -> std::result::Result<(), Diagnostic> {
+        let span = statement.span;
+        let result = match &statement.node {
+            // Blocks report errors against the statement inside them that failed.
+            Statement::If { condition, then_branch, else_branch } => {
+                let holds = self.evaluate_expression(condition).map_err(|message| self.runtime_error(message, span))?.is_truthy();
+                return self.execute_block(if holds { then_branch } else { else_branch });
+            }
+            Statement::ForEach { variable, iterable, body } => {
+                let (name, items) = self.loop_items(variable, iterable).map_err(|message| self.runtime_error(message, span))?;
+                for item in items {
+                    self.execute_in_scope(body, |env| env.define(name.clone(), item))?;
+                    if self.interrupted() { break; }
+                }
+                return Ok(());
+            }
+            Statement::Repeat { count, body } => {
+                let times = match self.evaluate_expression(count).map_err(|message| self.runtime_error(message, span))? {
+                    TaleaValue::Number(n) if n >= 0 => n,
+                    TaleaValue::Number(n) => return Err(Diagnostic::new(format!("`repeat` needs a count of 0 or more, but got {}.", n), span)),
+                    other => return Err(Diagnostic::new(format!("`repeat` needs a whole number of times, but got {}.", other.type_name()), span)),
+                };
+                for _ in 0..times {
+                    self.execute_in_scope(body, |_| {})?;
+                    if self.interrupted() { break; }
+                }
+                return Ok(());
+            }
+            Statement::Pipeline(stages) => return self.execute_block(stages),
             Statement::Use(backend) => self.execute_use_statement(backend),
             Statement::Summarize { source, destination } => self.execute_summarize_statement(source, destination),
             Statement::Tag { source, method, destination } => self.execute_tag_statement(source, method, destination),
             Statement::Define { name, value } => self.execute_define_statement(name, value),
+            Statement::Assign { name, value } => self.execute_assign_statement(name, value),
             Statement::Arithmetic { op, value, target, destination } => self.execute_arithmetic_statement(op, value, target, destination),
             Statement::Load { source, alias } => self.execute_load_statement(source, alias),
+            Statement::LoadStopwords { path, destination } => self.execute_load_stopwords_statement(path, destination),
             Statement::Save { source, destination } => self.execute_save_statement(source, destination),
             Statement::Print(expression) => self.execute_print_statement(expression),
             Statement::Tokenize { source, destination } => self.execute_tokenize_statement(source, destination),
             Statement::Count { unit, source, destination } => self.execute_count_statement(unit, source, destination),
             Statement::Lemmatize { source, destination } => self.execute_lemmatize_statement(source, destination),
             Statement::Filter { source, condition, destination } => self.execute_filter_statement(source, condition, destination),
+            Statement::SetPrecision(precision) => self.execute_set_precision_statement(precision),
+            Statement::Select { columns, source, destination } => self.execute_select_statement(columns, source, destination),
+            Statement::Sort { source, keys, descending, destination } => self.execute_sort_statement(source, keys, *descending, destination),
+            Statement::Frequency { unit, source, ignore_case, destination } => self.execute_frequency_statement(unit, source, *ignore_case, destination),
+            Statement::Rank { source, key, ascending, destination } => self.execute_rank_statement(source, key, *ascending, destination),
+            Statement::Concordance { keyword, source, width, sorted_by, destination } => self.execute_concordance_statement(keyword, source, width.as_ref(), *sorted_by, destination),
+            Statement::Find { pattern, literal, source, ignore_case, destination } => self.execute_find_statement(pattern, *literal, source, *ignore_case, destination),
+            Statement::Replace { pattern, replacement, literal, source, ignore_case, destination } => self.execute_replace_statement(pattern, replacement, *literal, source, *ignore_case, destination),
+            Statement::ChangeCase { source, upper, destination } => self.execute_cleaning(source, None, destination, |value, _| cleaning::change_case(value, *upper)),
+            Statement::Remove { what, stopword_list, source, destination } => self.execute_cleaning(source, stopword_list.as_deref(), destination, |value, stopwords| cleaning::run_step(value, *what, stopwords)),
+            Statement::Normalize { source, compatibility, destination } => self.execute_cleaning(source, None, destination, |value, _| cleaning::normalize(value, *compatibility)),
+            Statement::Clean { source, steps, stopword_list, destination } => self.execute_cleaning(source, stopword_list.as_deref(), destination, |value, stopwords| cleaning::clean(value, steps, stopwords)),
+            Statement::Ngrams { size, source, skip_stopwords, stopword_list, counted, destination } => {
+                self.execute_ngrams_statement(size, source, skip_stopwords.then_some(stopword_list.as_deref()), *counted, destination)
+            }
+            Statement::Collocate { node, source, window, ranked_by, destination } => self.execute_collocate_statement(node, source, window.as_ref(), ranked_by.as_deref(), destination),
+            Statement::DefineProcedure { name, parameters, body } => self.execute_define_procedure_statement(name, parameters, body),
+            Statement::Call { name, arguments, destination } => return self.execute_call_statement(name, arguments, destination, span),
+            Statement::Return(value) => self.execute_return_statement(value),
+            // Exiting happens at run time, so everything before `exit` in a script still runs.
+            Statement::Exit => { self.exiting = true; Ok(()) }
+        };
+        result.map_err(|message| self.runtime_error(message, span))
+    }
+
+    fn interrupted(&self) -> bool { self.returning.is_some() || self.exiting }
+
+    // A runtime error in the statement at `span`, narrowed to the operand that
+    // caused it when the expression said which one.
+    fn runtime_error(&mut self, message: String, span: Span) -> Diagnostic {
+        Diagnostic::new(message, self.error_span.take().unwrap_or(span))
+    }
+
+    // Runs a block body in a fresh scope, so its variables (like the loop
+    // variable) disappear when it finishes, even if it fails.
+    fn execute_in_scope(&mut self, body: &[Spanned<Statement>], setup: impl FnOnce(&mut Environment)) -> std::result::Result<(), Diagnostic> {
+        self.environment.push_scope();
+        setup(&mut self.environment);
+        let result = self.execute_block(body);
+        self.environment.pop_scope();
+        result
+    }
+
+    fn execute_define_procedure_statement(&mut self, name: &Expression, parameters: &[String], body: &Rc<Vec<Spanned<Statement>>>) -> std::result::Result<(), String> {
+        let name = self.get_identifier_name(name)?;
+        let procedure = Procedure { name: name.clone(), parameters: parameters.to_vec(), body: Rc::clone(body), closure: self.environment.current_frame() };
+        self.environment.define(name, TaleaValue::Procedure(Rc::new(procedure)));
+        Ok(())
+    }
+
+    // Arguments are evaluated in the caller's scope; the body then runs in a new
+    // scope inside the procedure's closure, so it sees the variables from where it
+    // was defined, not the caller's, and its own variables are gone once it returns.
+    fn execute_call_statement(&mut self, name: &Expression, arguments: &[Expression], destination: &Option<Expression>, span: Span) -> std::result::Result<(), Diagnostic> {
+        let procedure = self.called_procedure(name, arguments.len()).map_err(|message| self.runtime_error(message, span))?;
+        let values = arguments.iter().map(|argument| self.evaluate_expression(argument))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|message| self.runtime_error(message, span))?;
+
+        self.call_depth += 1;
+        let parameters = procedure.parameters.iter().cloned().zip(values);
+        let caller = self.environment.enter_procedure(&procedure.closure);
+        for (parameter, value) in parameters { self.environment.define(parameter, value); }
+        let outcome = self.execute_block(&procedure.body);
+        self.environment.restore(caller);
+        self.call_depth -= 1;
+        let returned = self.returning.take().unwrap_or(TaleaValue::Null);
+        // Errors inside the body keep pointing at the statement that failed;
+        // each call on the way out adds itself to the trace.
+        outcome.map_err(|diagnostic| diagnostic.called_from(&procedure.name, span))?;
+        if self.exiting { return Ok(()); }
+
+        match destination {
+            Some(destination) => {
+                let dest_name = self.get_identifier_name(destination).map_err(|message| self.runtime_error(message, span))?;
+                self.store_result(dest_name, returned);
+            }
+            // A procedure that returns nothing leaves `it` as it was.
+            None if !matches!(returned, TaleaValue::Null) => self.store_result(IMPLICIT_RESULT.to_string(), returned),
+            None => {}
+        }
+        Ok(())
+    }
+
+    // The procedure a call names, checked against the number of values it was given.
+    fn called_procedure(&self, name: &Expression, argument_count: usize) -> std::result::Result<Rc<Procedure>, String> {
+        let name = self.get_identifier_name(name)?;
+        let procedure = match self.environment.get(&name) {
+            Some(TaleaValue::Procedure(procedure)) => procedure,
+            Some(other) => return Err(format!("'{}' is {}, not a procedure.", name, other.type_name())),
+            None => return Err(self.procedure_not_found(&name)),
+        };
+        if argument_count != procedure.parameters.len() {
+            return Err(format!(
+                "The procedure '{}' takes {} value(s), but was given {}.",
+                procedure.name, procedure.parameters.len(), argument_count
+            ));
+        }
+        if self.call_depth >= MAX_CALL_DEPTH {
+            return Err(format!("Procedures called each other more than {} levels deep. Does '{}' call itself forever?", MAX_CALL_DEPTH, procedure.name));
+        }
+        Ok(procedure)
+    }
+
+    fn execute_return_statement(&mut self, value: &Option<Expression>) -> std::result::Result<(), String> {
+        if self.call_depth == 0 {
+            return Err("`return` can only be used inside a procedure.".to_string());
+        }
+        let value = match value {
+            Some(expression) => self.evaluate_expression(expression)?,
+            None => TaleaValue::Null,
+        };
+        self.returning = Some(value);
+        Ok(())
+    }
+
+    fn loop_items(&mut self, variable: &Expression, iterable: &Expression) -> std::result::Result<(String, Vec<TaleaValue>), String> {
+        let name = self.get_identifier_name(variable)?;
+        match self.evaluate_expression(iterable)? {
+            TaleaValue::List(items) | TaleaValue::Tuple(items) => Ok((name, items)),
+            TaleaValue::String(_) => Err("`for each` can't loop over text directly. Tokenize it first to get a list of words.".to_string()),
+            TaleaValue::Table(table) => Ok((name, table.records())),
+            TaleaValue::Map(_) => Err("`for each` can't loop over a record directly. Loop over `keys of` or `values of` it instead.".to_string()),
+            other => Err(format!("`for each` needs a list to loop over, but got {}.", other.type_name())),
         }
     }
 
@@ -68,10 +278,10 @@ impl Interpreter {
         let dest_name = self.get_identifier_name(destination)?;
         let numbers_as_strings: Vec<String> = source_list.iter()
             .filter_map(|val| {
-                if let TaleaValue::Number(n) = val {
-                    Some(n.to_string())
-                } else {
-                    None
+                match val {
+                    TaleaValue::Number(n) => Some(n.to_string()),
+                    TaleaValue::Float(f) => Some(f.to_string()),
+                    _ => None,
                 }
             })
             .collect();
@@ -90,7 +300,7 @@ impl Interpreter {
                 .unwrap_or_default()
                 .join("\n");
             
-            self.environment.define(dest_name, TaleaValue::String(summary_output));
+            self.store_result(dest_name, TaleaValue::String(summary_output));
         }
             
         Ok(())
@@ -116,24 +326,32 @@ impl Interpreter {
             let doc = nlp.call1((&text,))?;
             let result_list = match method_token {
                 Token::POS => {
-                    doc.iter()?.map(|token_result| {
+                    let tuples = doc.iter()?.map(|token_result| {
                         let token = token_result?;
                         let text = token.getattr("text")?.extract::<String>()?;
                         let pos = token.getattr("pos_")?.extract::<String>()?;
                         Ok(TaleaValue::Tuple(vec![TaleaValue::String(text), TaleaValue::String(pos)]))
-                    }).collect::<PyResult<Vec<TaleaValue>>>()?
+                    }).collect::<PyResult<Vec<TaleaValue>>>()?;
+                    Table::from_tuples(vec!["text".to_string(), "pos".to_string()], &tuples)
+                },
+                // One record per token, with the attributes people most often need.
+                Token::Tokens => {
+                    let records = doc.iter()?.map(|token_result| spacy_token_record(&token_result?)).collect::<PyResult<Vec<TaleaValue>>>()?;
+                    Table::from_records(&records)
                 },
                 Token::NER => {
-                    doc.getattr("ents")?.iter()?.map(|ent_result| {
+                    let tuples = doc.getattr("ents")?.iter()?.map(|ent_result| {
                         let ent = ent_result?;
                         let text = ent.getattr("text")?.extract::<String>()?;
                         let label = ent.getattr("label_")?.extract::<String>()?;
                         Ok(TaleaValue::Tuple(vec![TaleaValue::String(text), TaleaValue::String(label)]))
-                    }).collect::<PyResult<Vec<TaleaValue>>>()?
+                    }).collect::<PyResult<Vec<TaleaValue>>>()?;
+                    Table::from_tuples(vec!["text".to_string(), "label".to_string()], &tuples)
                 },
                 _ => return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>("Unsupported tagging method"))
             };
-            self.environment.define(dest_name, TaleaValue::List(result_list));
+            let table = result_list.map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+            self.store_result(dest_name, TaleaValue::Table(table));
             Ok(())
         }).map_err(|e| format!("Python Error: {}", e))?;
         println!("[Interpreter: Tagging complete.]");
@@ -155,7 +373,7 @@ impl Interpreter {
                 let lemma = token.getattr("lemma_")?.extract::<String>()?;
                 Ok(TaleaValue::String(lemma))
             }).collect::<PyResult<Vec<TaleaValue>>>()?;
-            self.environment.define(dest_name, TaleaValue::List(lemmas));
+            self.store_result(dest_name, TaleaValue::List(lemmas));
             Ok(())
         }).map_err(|e| format!("Python Error: {}", e))?;
         println!("[Interpreter: Lemmatization complete.]");
@@ -163,27 +381,280 @@ impl Interpreter {
     }
 
     fn execute_filter_statement(&mut self, source: &Expression, condition: &FilterCondition, destination: &Expression) -> std::result::Result<(), String> {
+        if let FilterCondition::Where(condition) = condition {
+            return self.execute_filter_rows(source, condition, destination);
+        }
         let source_list = if let TaleaValue::List(l) = self.evaluate_expression(source)? { l } else { return Err("Filter can only be applied to a list.".to_string()); };
         let dest_name = self.get_identifier_name(destination)?;
+        // Compiled once, not once per item.
+        let regex = match condition {
+            FilterCondition::Matching(expr) => match self.evaluate_expression(expr)? {
+                TaleaValue::String(pattern) => Some(patterns::compile(&pattern, false)?),
+                other => return Err(format!("`matching` needs the pattern as text, but got {}.", other.type_name())),
+            },
+            _ => None,
+        };
         let mut filtered_list = Vec::new();
         for item in source_list {
             if let Some(s_item) = item.as_string() {
                 let passes = match condition {
+                    FilterCondition::Matching(_) => regex.as_ref().is_some_and(|regex| regex.is_match(&s_item)),
                     FilterCondition::Containing(expr) => self.evaluate_expression(expr)?.as_string().map_or(false, |p| s_item.contains(&p)),
                     FilterCondition::StartingWith(expr) => self.evaluate_expression(expr)?.as_string().map_or(false, |p| s_item.starts_with(&p)),
                     FilterCondition::EndingWith(expr) => self.evaluate_expression(expr)?.as_string().map_or(false, |p| s_item.ends_with(&p)),
+                    FilterCondition::Where(_) => unreachable!("handled by execute_filter_rows"),
                 };
                 if passes { filtered_list.push(item); }
             }
         }
         println!("[Interpreter: Filtered list contains {} items.]", filtered_list.len());
-        self.environment.define(dest_name, TaleaValue::List(filtered_list));
+        self.store_result(dest_name, TaleaValue::List(filtered_list));
+        Ok(())
+    }
+
+    // `filter freq where count > 10`: the condition is checked once per row, in a
+    // scope where each column is a variable holding that row's cell.
+    fn execute_filter_rows(&mut self, source: &Expression, condition: &Expression, destination: &Expression) -> std::result::Result<(), String> {
+        let table = match self.evaluate_expression(source)? {
+            TaleaValue::Table(table) => table,
+            TaleaValue::List(items) if items.iter().all(|item| matches!(item, TaleaValue::Map(_))) => Table::from_records(&items)?,
+            other => return Err(format!("`filter ... where` works on tables, but got {}. Use `containing`, `starting_with` or `ending_with` for a list of words.", other.type_name())),
+        };
+        let dest_name = self.get_identifier_name(destination)?;
+        let mut rows = Vec::new();
+        for row in &table.rows {
+            self.environment.push_scope();
+            for (column, cell) in table.columns.iter().zip(row) {
+                self.environment.define(column.name.clone(), cell.clone());
+            }
+            let keep = self.evaluate_expression(condition);
+            self.environment.pop_scope();
+            if keep?.is_truthy() { rows.push(row.clone()); }
+        }
+        println!("[Interpreter: Kept {} of {} rows.]", rows.len(), table.rows.len());
+        self.store_result(dest_name, TaleaValue::Table(table.with_rows(rows)));
+        Ok(())
+    }
+
+    fn execute_select_statement(&mut self, columns: &[String], source: &Expression, destination: &Expression) -> std::result::Result<(), String> {
+        let table = as_table(self.evaluate_expression(source)?)?;
+        let dest_name = self.get_identifier_name(destination)?;
+        self.store_result(dest_name, TaleaValue::Table(table.select(columns)?));
+        Ok(())
+    }
+
+    // Tables sort by one or more columns, lists by their items. Sorting is
+    // stable: rows that compare equal on every key keep their original order.
+    fn execute_sort_statement(&mut self, source: &Expression, keys: &[SortKey], descending: bool, destination: &Expression) -> std::result::Result<(), String> {
+        let value = self.evaluate_expression(source)?;
+        let dest_name = self.get_identifier_name(destination)?;
+        let mut order = CellOrder::default();
+        let sorted = match value {
+            TaleaValue::Table(table) => {
+                if keys.is_empty() {
+                    return Err(format!("Say which column to sort the table by, e.g. `sort ... by {}`.", table.columns.first().map_or("count", |c| c.name.as_str())));
+                }
+                let columns = keys.iter()
+                    .map(|key| table.column_index(&key.column).map(|index| (index, key.descending)))
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                let mut rows = table.rows.clone();
+                rows.sort_by(|a, b| columns.iter().fold(std::cmp::Ordering::Equal, |ordering, &(index, descending)| {
+                    ordering.then_with(|| {
+                        let ordering = order.compare(&a[index], &b[index]);
+                        if descending { ordering.reverse() } else { ordering }
+                    })
+                }));
+                TaleaValue::Table(table.with_rows(rows))
+            }
+            TaleaValue::List(mut items) if keys.is_empty() => {
+                items.sort_by(|a, b| {
+                    let ordering = order.compare(a, b);
+                    if descending { ordering.reverse() } else { ordering }
+                });
+                TaleaValue::List(items)
+            }
+            TaleaValue::List(_) => return Err("Only tables can be sorted `by` a column. Use `table of` to turn the list into a table first.".to_string()),
+            other => return Err(format!("`sort` works on lists and tables, but got {}.", other.type_name())),
+        };
+        self.store_result(dest_name, sorted);
+        Ok(())
+    }
+
+    fn execute_frequency_statement(&mut self, unit: &Expression, source: &Expression, ignore_case: bool, destination: &Expression) -> std::result::Result<(), String> {
+        let unit_token = if let Expression::Unit(t) = unit { t } else { return Err("Invalid unit for frequency".to_string()); };
+        let source_val = self.evaluate_expression(source)?;
+        let dest_name = self.get_identifier_name(destination)?;
+        let words = words_of(&source_val)?;
+        let (items, column) = match unit_token {
+            Token::Words | Token::Tokens => (words, "word"),
+            Token::Characters => (words.iter().flat_map(|word| word.chars()).map(|c| c.to_string()).collect(), "character"),
+            Token::Bigrams => (ngrams_of(&words, 2, None), "bigram"),
+            Token::Trigrams => (ngrams_of(&words, 3, None), "trigram"),
+            other => return Err(format!("`frequency` can count words or characters, not {}.", other.describe())),
+        };
+        let table = frequency_table(&items, column, ignore_case);
+        println!("[Interpreter: Found {} different {}s in {} {}s.]", table.rows.len(), column, items.len(), column);
+        self.store_result(dest_name, TaleaValue::Table(table));
+        Ok(())
+    }
+
+    // The source is a text, a list of words, or a record with one of those per
+    // document. A single document is named after its variable.
+    fn execute_concordance_statement(&mut self, keyword: &Expression, source: &Expression, width: Option<&Expression>, sorted_by: Option<ContextSide>, destination: &Expression) -> std::result::Result<(), String> {
+        let keyword = match self.evaluate_expression(keyword)? {
+            TaleaValue::String(keyword) => keyword,
+            other => return Err(format!("`concordance` needs the keyword as text, but got {}.", other.type_name())),
+        };
+        let documents = documents_of(if let Expression::Identifier(name) = source { name } else { "text" }, &self.evaluate_expression(source)?)?;
+        let width = match width {
+            Some(width) => whole_number(&self.evaluate_expression(width)?, "width")?,
+            None => DEFAULT_CONTEXT_WIDTH,
+        };
+        let dest_name = self.get_identifier_name(destination)?;
+        let table = concordance_table(&documents, &keyword, width, sorted_by)?;
+        println!("[Interpreter: Found {} {} of '{}'.]", table.rows.len(), if table.rows.len() == 1 { "occurrence" } else { "occurrences" }, keyword);
+        self.store_result(dest_name, TaleaValue::Table(table));
+        Ok(())
+    }
+
+    // The cleaning commands differ only in what they do to the value.
+    fn execute_cleaning(&mut self, source: &Expression, stopword_list: Option<&str>, destination: &Expression, step: impl FnOnce(&TaleaValue, &HashSet<String>) -> std::result::Result<TaleaValue, String>) -> std::result::Result<(), String> {
+        let value = self.evaluate_expression(source)?;
+        let dest_name = self.get_identifier_name(destination)?;
+        let cleaned = step(&value, &self.stopword_set(stopword_list)?)?;
+        self.store_result(dest_name, cleaned);
+        Ok(())
+    }
+
+    // `None` is the project's list; otherwise a list variable or a built-in language.
+    fn stopword_set(&self, list: Option<&str>) -> std::result::Result<HashSet<String>, String> {
+        let Some(name) = list else { return Ok(self.stopwords.clone()) };
+        match self.environment.get(name) {
+            Some(TaleaValue::List(words)) => Ok(stopwords::set_of(&words_of(&TaleaValue::List(words))?)),
+            _ => stopwords::builtin(name).ok_or_else(|| match suggest(name, stopwords::LANGUAGES.iter().copied()) {
+                Some(similar) => format!("There is no stopword list for '{}'. Did you mean '{}'?", name, similar),
+                None => format!("There is no stopword list for '{}'. The built-in lists are: {}.", name, stopwords::LANGUAGES.join(", ")),
+            }),
+        }
+    }
+
+    // The loaded words replace the project's stopwords, and are also kept as a list.
+    fn execute_load_stopwords_statement(&mut self, path: &Expression, destination: &Expression) -> std::result::Result<(), String> {
+        let raw_path = self.get_string_value(path)?;
+        let file_path = std::path::PathBuf::from(shellexpand::tilde(&raw_path).as_ref());
+        let text = fs::read_to_string(&file_path).map_err(|e| format!("Failed to read stopword list '{}': {}", file_path.display(), e))?;
+        let words = stopwords::parse_list(&text);
+        let dest_name = self.get_identifier_name(destination)?;
+        println!("[Interpreter: Loaded {} stopwords from '{}'.]", words.len(), file_path.display());
+        self.stopwords = stopwords::set_of(&words);
+        self.store_result(dest_name, TaleaValue::List(words.into_iter().map(TaleaValue::String).collect()));
+        Ok(())
+    }
+
+    fn execute_find_statement(&mut self, pattern: &Expression, literal: bool, source: &Expression, ignore_case: bool, destination: &Expression) -> std::result::Result<(), String> {
+        let pattern = match self.evaluate_expression(pattern)? {
+            TaleaValue::String(pattern) => pattern,
+            other => return Err(format!("`find` needs the pattern as text, but got {}.", other.type_name())),
+        };
+        let regex = patterns::compile(&if literal { regex::escape(&pattern) } else { pattern }, ignore_case)?;
+        let table = patterns::matches_table(&self.evaluate_expression(source)?, &regex)?;
+        let dest_name = self.get_identifier_name(destination)?;
+        println!("[Interpreter: Found {} {}.]", table.rows.len(), if table.rows.len() == 1 { "match" } else { "matches" });
+        self.store_result(dest_name, TaleaValue::Table(table));
+        Ok(())
+    }
+
+    fn execute_replace_statement(&mut self, pattern: &Expression, replacement: &Expression, literal: bool, source: &Expression, ignore_case: bool, destination: &Expression) -> std::result::Result<(), String> {
+        let (pattern, replacement) = match (self.evaluate_expression(pattern)?, self.evaluate_expression(replacement)?) {
+            (TaleaValue::String(pattern), TaleaValue::String(replacement)) => (pattern, replacement),
+            (TaleaValue::String(_), other) | (other, _) => return Err(format!("`replace` needs text to find and text to put in its place, but got {}.", other.type_name())),
+        };
+        if pattern.is_empty() { return Err("`replace` needs something to look for, but the text to replace is empty.".to_string()); }
+        let regex = patterns::compile(&if literal { regex::escape(&pattern) } else { pattern }, ignore_case)?;
+        let (replaced, count) = patterns::replace_in(&self.evaluate_expression(source)?, &regex, &replacement, literal)?;
+        let dest_name = self.get_identifier_name(destination)?;
+        println!("[Interpreter: Made {} {}.]", count, if count == 1 { "replacement" } else { "replacements" });
+        self.store_result(dest_name, replaced);
+        Ok(())
+    }
+
+    // Without `with counts` the n-grams are a list in text order; with it, a
+    // frequency list. A record of documents is treated document by document.
+    // `skip_stopwords` is the stopword list to skip (see `stopword_set`), if any.
+    fn execute_ngrams_statement(&mut self, size: &Expression, source: &Expression, skip_stopwords: Option<Option<&str>>, counted: bool, destination: &Expression) -> std::result::Result<(), String> {
+        let size = whole_number(&self.evaluate_expression(size)?, "ngrams of size")?;
+        if size == 0 { return Err("N-grams need a size of at least 1.".to_string()); }
+        let documents = documents_of("text", &self.evaluate_expression(source)?)?;
+        let dest_name = self.get_identifier_name(destination)?;
+        let stopwords = skip_stopwords.map(|list| self.stopword_set(list)).transpose()?;
+        let ngrams: Vec<String> = documents.iter().flat_map(|(_, words)| ngrams_of(words, size, stopwords.as_ref())).collect();
+        let column = match size { 2 => "bigram", 3 => "trigram", _ => "ngram" };
+        println!("[Interpreter: Extracted {} {}s.]", ngrams.len(), column);
+        let value = if counted {
+            TaleaValue::Table(frequency_table(&ngrams, column, false))
+        } else {
+            TaleaValue::List(ngrams.into_iter().map(TaleaValue::String).collect())
+        };
+        self.store_result(dest_name, value);
+        Ok(())
+    }
+
+    // Collocates are ranked by log-likelihood unless another measure is given.
+    fn execute_collocate_statement(&mut self, node: &Expression, source: &Expression, window: Option<&Expression>, ranked_by: Option<&str>, destination: &Expression) -> std::result::Result<(), String> {
+        let node = match self.evaluate_expression(node)? {
+            TaleaValue::String(node) => node,
+            other => return Err(format!("`collocate` needs the word as text, but got {}.", other.type_name())),
+        };
+        let documents = documents_of(if let Expression::Identifier(name) = source { name } else { "text" }, &self.evaluate_expression(source)?)?;
+        let window = match window {
+            Some(window) => whole_number(&self.evaluate_expression(window)?, "window")?,
+            None => DEFAULT_COLLOCATION_WINDOW,
+        };
+        let measure = ranked_by.unwrap_or("log_likelihood");
+        if !ASSOCIATION_MEASURES.contains(&measure) {
+            return Err(match suggest(measure, ASSOCIATION_MEASURES.iter().copied()) {
+                Some(similar) => format!("Unknown association measure '{}'. Did you mean '{}'?", measure, similar),
+                None => format!("Unknown association measure '{}'. Collocates can be ranked by {}.", measure, ASSOCIATION_MEASURES.join(", ")),
+            });
+        }
+        let dest_name = self.get_identifier_name(destination)?;
+        let table = collocation_table(&documents, &node, window, measure)?;
+        println!("[Interpreter: Found {} different words within {} of '{}'.]", table.rows.len(), if window == 1 { "1 word".to_string() } else { format!("{} words", window) }, node);
+        self.store_result(dest_name, TaleaValue::Table(table));
+        Ok(())
+    }
+
+    fn execute_rank_statement(&mut self, source: &Expression, key: &str, ascending: bool, destination: &Expression) -> std::result::Result<(), String> {
+        let table = as_table(self.evaluate_expression(source)?)?;
+        let dest_name = self.get_identifier_name(destination)?;
+        self.store_result(dest_name, TaleaValue::Table(table.ranked_by(key, !ascending)?));
         Ok(())
     }
 
-    fn execute_save_statement(&mut self, source: &Expression, destination: &Expression) -> std::result::Result<(), String> { let source_val = self.evaluate_expression(source)?; let file_path = self.get_string_value(destination)?; let content_to_save = source_val.to_string(); fs::write(&file_path, content_to_save).map_err(|e| format!("Failed to write to file '{}': {}", file_path, e))?; println!("[Interpreter: Successfully saved content to '{}']", file_path); Ok(()) }
-    fn execute_arithmetic_statement(&mut self, op: &ArithmeticOp, value: &Expression, target: &Expression, destination: &Option<Expression>) -> std::result::Result<(), String> { let target_name = self.get_identifier_name(target)?; let current_val = self.environment.get(&target_name).ok_or_else(|| format!("Variable '{}' not found.", target_name))?; let current_num = if let TaleaValue::Number(n) = current_val { n } else { return Err(format!("Cannot perform arithmetic on '{}'. Not a number.", target_name)); }; let operand_val = self.evaluate_expression(value)?; let operand_num = if let TaleaValue::Number(n) = operand_val { n } else { return Err("Arithmetic operations require a number.".to_string()); }; let result = match op { ArithmeticOp::Add => current_num + operand_num, ArithmeticOp::Subtract => current_num - operand_num, ArithmeticOp::Multiply => current_num * operand_num, ArithmeticOp::Divide => { if operand_num == 0 { return Err("Division by zero.".to_string()); } current_num / operand_num } }; let final_dest_name = if let Some(dest_expr) = destination { self.get_identifier_name(dest_expr)? } else { target_name.clone() }; self.environment.define(final_dest_name, TaleaValue::Number(result)); Ok(()) }
+    fn execute_save_statement(&mut self, source: &Expression, destination: &Expression) -> std::result::Result<(), String> { let source_val = self.evaluate_expression(source)?; let file_path = self.get_string_value(destination)?; let content_to_save = match &source_val {
+            // Tables are saved as CSV or TSV when the file name asks for it.
+            TaleaValue::Table(table) if file_path.to_lowercase().ends_with(".csv") => table.to_delimited(',', self.float_precision),
+            TaleaValue::Table(table) if file_path.to_lowercase().ends_with(".tsv") => table.to_delimited('\t', self.float_precision),
+            _ => source_val.display(self.float_precision),
+        }; fs::write(&file_path, content_to_save).map_err(|e| format!("Failed to write to file '{}': {}", file_path, e))?; println!("[Interpreter: Successfully saved content to '{}']", file_path); Ok(()) }
+    fn execute_arithmetic_statement(&mut self, op: &ArithmeticOp, value: &Expression, target: &Expression, destination: &Option<Expression>) -> std::result::Result<(), String> {
+        let target_name = self.get_identifier_name(target)?;
+        let current_val = self.lookup(&target_name).ok_or_else(|| self.variable_not_found(&target_name))?;
+        if !current_val.is_numeric() { return Err(format!("Cannot perform arithmetic on '{}'. Not a number.", target_name)); }
+        let operand_val = self.evaluate_expression(value)?;
+        let result = apply_arithmetic(op, &current_val, &operand_val)?;
+        let final_dest_name = if let Some(dest_expr) = destination { self.get_identifier_name(dest_expr)? } else { target_name.clone() };
+        self.store_result(final_dest_name, result);
+        Ok(())
+    }
+    fn execute_set_precision_statement(&mut self, precision: &Expression) -> std::result::Result<(), String> {
+        match self.evaluate_expression(precision)? {
+            TaleaValue::Number(n) if (0..=MAX_PRECISION as i64).contains(&n) => { self.float_precision = Some(n as usize); Ok(()) }
+            _ => Err(format!("Precision must be a whole number of decimal places between 0 and {}.", MAX_PRECISION)),
+        }
+    }
     fn execute_define_statement(&mut self, name: &Expression, value: &Expression) -> std::result::Result<(), String> { let var_name = self.get_identifier_name(name)?; let value = self.evaluate_expression(value)?; self.environment.define(var_name, value); Ok(()) }
+    fn execute_assign_statement(&mut self, name: &Expression, value: &Expression) -> std::result::Result<(), String> { let var_name = self.get_identifier_name(name)?; let value = self.evaluate_expression(value)?; self.environment.assign(&var_name, value) }
     fn execute_load_statement(&mut self, source: &Expression, alias: &Expression) -> std::result::Result<(), String> {
                 let var_name = self.get_identifier_name(alias)?;
                 let raw_path = self.get_string_value(source)?;
@@ -201,10 +672,10 @@ impl Interpreter {
                     .map_err(|e| format!("Failed to read file '{}': {}", file_path.display(), e))?;
 
                 println!("[Interpreter: Successfully read {} bytes]", file_content.len());
-                self.environment.define(var_name, TaleaValue::String(file_content));
+                self.store_result(var_name, TaleaValue::String(file_content));
                 Ok(())
             }
-    fn execute_tokenize_statement(&mut self, source: &Expression, destination: &Expression) -> std::result::Result<(), String> { let source_val = self.evaluate_expression(source)?; let dest_name = self.get_identifier_name(destination)?; if let TaleaValue::String(s) = source_val { let tokens: Vec<TaleaValue> = s.split_whitespace().map(|word| TaleaValue::String(word.to_string())).collect(); println!("[Interpreter: Tokenized text into {} tokens.]", tokens.len()); self.environment.define(dest_name, TaleaValue::List(tokens)); Ok(()) } else { Err("The 'tokenize' command can only be used on a String value.".to_string()) } }
+    fn execute_tokenize_statement(&mut self, source: &Expression, destination: &Expression) -> std::result::Result<(), String> { let source_val = self.evaluate_expression(source)?; let dest_name = self.get_identifier_name(destination)?; if let TaleaValue::String(s) = source_val { let tokens: Vec<TaleaValue> = s.split_whitespace().map(|word| TaleaValue::String(word.to_string())).collect(); println!("[Interpreter: Tokenized text into {} tokens.]", tokens.len()); self.store_result(dest_name, TaleaValue::List(tokens)); Ok(()) } else { Err("The 'tokenize' command can only be used on a String value.".to_string()) } }
     fn execute_count_statement(&mut self, unit: &Expression, source: &Expression, destination: &Expression) -> std::result::Result<(), String> {
             let unit_token = if let Expression::Unit(t) = unit { t } else { return Err("Invalid unit for count".to_string()); };
             let source_val = self.evaluate_expression(source)?;
@@ -215,18 +686,18 @@ impl Interpreter {
                 (Token::Words | Token::Tokens, TaleaValue::List(l)) => {
                     let count = l.len() as i64;
                     println!("[Interpreter: Counted {} items]", count);
-                    self.environment.define(dest_name, TaleaValue::Number(count));
+                    self.store_result(dest_name, TaleaValue::Number(count));
                 },
                 // CASE 2: Get character/line count from a single string
                 (Token::Characters, TaleaValue::String(s)) => {
                     let count = s.len() as i64;
                     println!("[Interpreter: Counted {} characters]", count);
-                    self.environment.define(dest_name, TaleaValue::Number(count));
+                    self.store_result(dest_name, TaleaValue::Number(count));
                 },
                 (Token::Lines, TaleaValue::String(s)) => {
                     let count = s.lines().count() as i64;
                     println!("[Interpreter: Counted {} lines]", count);
-                    self.environment.define(dest_name, TaleaValue::Number(count));
+                    self.store_result(dest_name, TaleaValue::Number(count));
                 },
                 
                 // CASE 3: Get character count for *each item* in a list
@@ -240,45 +711,462 @@ impl Interpreter {
                         TaleaValue::Number(len)
                     }).collect();
                     println!("[Interpreter: Generated list of {} character counts]", lengths.len());
-                    self.environment.define(dest_name, TaleaValue::List(lengths));
+                    self.store_result(dest_name, TaleaValue::List(lengths));
+                },
+                (Token::Bigrams | Token::Trigrams, value) => {
+                    let size = if *unit_token == Token::Bigrams { 2 } else { 3 };
+                    let count = ngrams_of(&words_of(&value)?, size, None).len() as i64;
+                    println!("[Interpreter: Counted {} {}]", count, unit_token.spelling());
+                    self.store_result(dest_name, TaleaValue::Number(count));
                 },
                 _ => return Err(format!("Cannot count {:?} in the provided variable type.", unit_token)),
             }
             Ok(())
         }
-    fn execute_print_statement(&mut self, expression: &Expression) -> std::result::Result<(), String> { let value = self.evaluate_expression(expression)?; println!("{}", value.to_string()); Ok(()) }
-    fn evaluate_expression(&mut self, expression: &Expression) -> std::result::Result<TaleaValue, String> { match expression { Expression::StringLiteral(s) => Ok(TaleaValue::String(s.clone())), Expression::Number(n) => Ok(TaleaValue::Number(*n)), Expression::Identifier(name) => self.environment.get(name).ok_or_else(|| format!("Variable '{}' not found.", name)), Expression::Unit(token) => Ok(TaleaValue::Unit(token.clone())), } }
+    fn execute_print_statement(&mut self, expression: &Expression) -> std::result::Result<(), String> { let value = self.evaluate_expression(expression)?; println!("{}", value.display(self.float_precision)); Ok(()) }
+    fn evaluate_expression(&mut self, expression: &Expression) -> std::result::Result<TaleaValue, String> {
+        match expression {
+            Expression::StringLiteral(s) => Ok(TaleaValue::String(s.clone())),
+            Expression::Number(n) => Ok(TaleaValue::Number(*n)),
+            Expression::Float(f) => Ok(TaleaValue::Float(*f)),
+            Expression::Boolean(b) => Ok(TaleaValue::Boolean(*b)),
+            Expression::Identifier(name) => self.lookup(name).ok_or_else(|| self.variable_not_found(name)),
+            Expression::Unit(token) => Ok(TaleaValue::Unit(token.clone())),
+            Expression::Arithmetic { op, left: left_expression, right: right_expression } => {
+                let left = self.evaluate_expression(&left_expression.node)?;
+                let right = self.evaluate_expression(&right_expression.node)?;
+                apply_arithmetic(op, &left, &right).inspect_err(|_| {
+                    let divides_by_zero = *op == ArithmeticOp::Divide && right.as_float() == Some(0.0);
+                    self.error_span = Some(if !left.is_numeric() {
+                        left_expression.span
+                    } else if !right.is_numeric() || divides_by_zero {
+                        right_expression.span
+                    } else {
+                        left_expression.span.to(right_expression.span)
+                    });
+                })
+            }
+            Expression::Comparison { op, left: left_expression, right: right_expression } => {
+                let left = self.evaluate_expression(&left_expression.node)?;
+                let right = self.evaluate_expression(&right_expression.node)?;
+                compare_values(op, &left, &right).map(TaleaValue::Boolean)
+                    .inspect_err(|_| self.error_span = Some(left_expression.span.to(right_expression.span)))
+            }
+            Expression::Contains { haystack, needle } => {
+                let haystack = self.evaluate_expression(haystack)?;
+                let needle = self.evaluate_expression(needle)?;
+                match (&haystack, &needle) {
+                    (TaleaValue::String(h), TaleaValue::String(n)) => Ok(TaleaValue::Boolean(h.contains(n.as_str()))),
+                    (TaleaValue::List(items) | TaleaValue::Tuple(items), _) => Ok(TaleaValue::Boolean(items.iter().any(|item| values_equal(item, &needle)))),
+                    (TaleaValue::Map(fields), TaleaValue::String(key)) => Ok(TaleaValue::Boolean(fields.iter().any(|(k, _)| k == key))),
+                    _ => Err(format!("Cannot check whether {} contains {}.", haystack.type_name(), needle.type_name())),
+                }
+            }
+            // `and`/`or` only evaluate their right side when they need to.
+            Expression::Logical { op, left, right } => {
+                let left = self.evaluate_expression(left)?.is_truthy();
+                let result = match op {
+                    LogicalOp::And => left && self.evaluate_expression(right)?.is_truthy(),
+                    LogicalOp::Or => left || self.evaluate_expression(right)?.is_truthy(),
+                };
+                Ok(TaleaValue::Boolean(result))
+            }
+            Expression::Not(inner) => Ok(TaleaValue::Boolean(!self.evaluate_expression(inner)?.is_truthy())),
+            Expression::Negate(inner) => match self.evaluate_expression(inner)? {
+                TaleaValue::Number(n) => n.checked_neg().map(TaleaValue::Number).ok_or_else(|| "The result is too large to represent.".to_string()),
+                TaleaValue::Float(f) => Ok(TaleaValue::Float(-f)),
+                _ => Err("Only numbers can be negated.".to_string()),
+            },
+            Expression::List(items) => Ok(TaleaValue::List(self.evaluate_all(items)?)),
+            Expression::Tuple(items) => Ok(TaleaValue::Tuple(self.evaluate_all(items)?)),
+            Expression::Map(fields) => {
+                let mut values = Vec::with_capacity(fields.len());
+                for (key, value) in fields { values.push((key.clone(), self.evaluate_expression(value)?)); }
+                Ok(TaleaValue::Map(values))
+            }
+            Expression::Item { index, collection } => {
+                let index = self.evaluate_expression(index)?;
+                let collection = self.evaluate_expression(collection)?;
+                item_of(&collection, &index)
+            }
+            Expression::Slice { end, count, collection } => {
+                let count = match count { Some(count) => Some(self.evaluate_expression(count)?), None => None };
+                let collection = self.evaluate_expression(collection)?;
+                slice_of(&collection, *end, count.as_ref())
+            }
+            Expression::Field { name, record } => field_of(&self.evaluate_expression(record)?, name),
+            Expression::Keys(record) => match self.evaluate_expression(record)? {
+                TaleaValue::Map(fields) => Ok(TaleaValue::List(fields.into_iter().map(|(key, _)| TaleaValue::String(key)).collect())),
+                // A table's keys are its column names.
+                TaleaValue::Table(table) => Ok(TaleaValue::List(table.columns.into_iter().map(|column| TaleaValue::String(column.name)).collect())),
+                other => Err(format!("`keys of` needs a record, but got {}.", other.type_name())),
+            },
+            Expression::Values(record) => match self.evaluate_expression(record)? {
+                TaleaValue::Map(fields) => Ok(TaleaValue::List(fields.into_iter().map(|(_, value)| value).collect())),
+                other => Err(format!("`values of` needs a record, but got {}.", other.type_name())),
+            },
+            Expression::Top { from_bottom, count, collection } => {
+                let count = self.evaluate_expression(count)?;
+                let collection = self.evaluate_expression(collection)?;
+                top_of(&collection, *from_bottom, &count)
+            }
+            Expression::ToTable(value) => Ok(TaleaValue::Table(as_table(self.evaluate_expression(value)?)?)),
+        }
+    }
+    fn evaluate_all(&mut self, expressions: &[Expression]) -> std::result::Result<Vec<TaleaValue>, String> {
+        expressions.iter().map(|expression| self.evaluate_expression(expression)).collect()
+    }
+    // Every command result is stored under its `as` name (if any) and also as
+    // `it`, so the next command or pipeline stage can pick it up. `result` is
+    // read through `it` rather than written, so a user's own `result` is kept.
+    fn store_result(&mut self, name: String, value: TaleaValue) {
+        if name != IMPLICIT_RESULT { self.environment.store(IMPLICIT_RESULT.to_string(), value.clone()); }
+        self.environment.store(name, value);
+    }
+    fn variable_not_found(&self, name: &str) -> String {
+        if name == IMPLICIT_RESULT || name == RESULT_ALIAS {
+            return "There is no previous result to work on yet. Name the value this command should use.".to_string();
+        }
+        let names = self.environment.names();
+        match suggest(name, names.iter().map(String::as_str)) {
+            Some(similar) => format!("Variable '{}' not found. Did you mean '{}'?", name, similar),
+            None => format!("Variable '{}' not found.", name),
+        }
+    }
+    fn procedure_not_found(&self, name: &str) -> String {
+        let procedures: Vec<String> = self.environment.variables().into_iter()
+            .filter(|(_, _, value)| matches!(value, TaleaValue::Procedure(_)))
+            .map(|(_, name, _)| name)
+            .collect();
+        match suggest(name, procedures.iter().map(String::as_str)) {
+            Some(similar) => format!("There is no procedure called '{}'. Did you mean '{}'?", name, similar),
+            None => format!("There is no command or procedure called '{}'.", name),
+        }
+    }
     fn get_identifier_name(&self, expression: &Expression) -> std::result::Result<String, String> { if let Expression::Identifier(name) = expression { Ok(name.clone()) } else { Err("Expected an identifier".to_string()) } }
     fn get_string_value(&mut self, expression: &Expression) -> std::result::Result<String, String> { if let TaleaValue::String(s) = self.evaluate_expression(expression)? { Ok(s) } else { Err("Expected a string value".to_string()) } }
 }
 
+// Integer arithmetic stays integer where the answer is exact; anything involving
+// a float, or a division that doesn't come out even, produces a float.
+fn apply_arithmetic(op: &ArithmeticOp, left: &TaleaValue, right: &TaleaValue) -> std::result::Result<TaleaValue, String> {
+    match (left, right) {
+        (TaleaValue::Number(a), TaleaValue::Number(b)) => {
+            let (a, b) = (*a, *b);
+            let result = match op {
+                ArithmeticOp::Add => a.checked_add(b),
+                ArithmeticOp::Subtract => a.checked_sub(b),
+                ArithmeticOp::Multiply => a.checked_mul(b),
+                ArithmeticOp::Divide => {
+                    if b == 0 { return Err("Division by zero.".to_string()); }
+                    // `checked_rem` fails only where `checked_div` does (i64::MIN / -1).
+                    match a.checked_rem(b) {
+                        Some(0) => a.checked_div(b),
+                        Some(_) => return Ok(TaleaValue::Float(a as f64 / b as f64)),
+                        None => None,
+                    }
+                }
+            };
+            result.map(TaleaValue::Number).ok_or_else(|| "The result is too large to represent.".to_string())
+        }
+        _ => {
+            let (a, b) = match (left.as_float(), right.as_float()) {
+                (Some(a), Some(b)) => (a, b),
+                _ => return Err("Arithmetic operations require a number.".to_string()),
+            };
+            let result = match op {
+                ArithmeticOp::Add => a + b,
+                ArithmeticOp::Subtract => a - b,
+                ArithmeticOp::Multiply => a * b,
+                ArithmeticOp::Divide => {
+                    if b == 0.0 { return Err("Division by zero.".to_string()); }
+                    a / b
+                }
+            };
+            Ok(TaleaValue::Float(result))
+        }
+    }
+}
+
+// The items of a list or tuple, or the characters of a text, for indexing.
+fn indexable_items(collection: &TaleaValue, what: &str) -> std::result::Result<Vec<TaleaValue>, String> {
+    match collection {
+        TaleaValue::List(items) | TaleaValue::Tuple(items) => Ok(items.clone()),
+        TaleaValue::String(s) => Ok(s.chars().map(|c| TaleaValue::String(c.to_string())).collect()),
+        TaleaValue::Table(table) => Ok(table.records()),
+        TaleaValue::Map(_) => Err(format!("`{}` works on lists; a record's fields are looked up by name.", what)),
+        other => Err(format!("`{}` needs a list or some text, but got {}.", what, other.type_name())),
+    }
+}
+
+fn whole_number(value: &TaleaValue, what: &str) -> std::result::Result<usize, String> {
+    match value {
+        TaleaValue::Number(n) if *n >= 0 => Ok(*n as usize),
+        TaleaValue::Number(_) => Err(format!("`{}` needs a number that isn't negative.", what)),
+        other => Err(format!("`{}` needs a whole number, but got {}.", what, other.type_name())),
+    }
+}
+
+// `table of ...`: a list of records, a list of tuples (columns `column1`,
+// `column2`, ...), a list of plain values (one `value` column) or a record (a
+// `key` and a `value` column).
+fn as_table(value: TaleaValue) -> std::result::Result<Table, String> {
+    match value {
+        TaleaValue::Table(table) => Ok(table),
+        TaleaValue::List(items) if items.iter().all(|item| matches!(item, TaleaValue::Map(_))) && !items.is_empty() => Table::from_records(&items),
+        TaleaValue::List(items) if items.iter().all(|item| matches!(item, TaleaValue::Tuple(_))) && !items.is_empty() => {
+            let width = items.iter().map(|item| if let TaleaValue::Tuple(cells) = item { cells.len() } else { 0 }).max().unwrap_or(0);
+            Table::from_tuples((1..=width).map(|i| format!("column{}", i)).collect(), &items)
+        }
+        TaleaValue::List(items) => Table::new(vec!["value".to_string()], items.into_iter().map(|item| vec![item]).collect()),
+        TaleaValue::Map(fields) => Table::new(
+            vec!["key".to_string(), "value".to_string()],
+            fields.into_iter().map(|(key, value)| vec![TaleaValue::String(key), value]).collect(),
+        ),
+        other => Err(format!("Cannot make a table from {}.", other.type_name())),
+    }
+}
+
+fn spacy_token_record(token: &Bound<'_, PyAny>) -> PyResult<TaleaValue> {
+    let text = |attribute: &str| -> PyResult<TaleaValue> { Ok(TaleaValue::String(token.getattr(attribute)?.extract::<String>()?)) };
+    Ok(TaleaValue::Map(vec![
+        ("text".to_string(), text("text")?),
+        ("lemma".to_string(), text("lemma_")?),
+        ("pos".to_string(), text("pos_")?),
+        ("tag".to_string(), text("tag_")?),
+        ("dep".to_string(), text("dep_")?),
+        ("is_stop".to_string(), TaleaValue::Boolean(token.getattr("is_stop")?.extract::<bool>()?)),
+    ]))
+}
+
+// `the label of entity`. On a list of records (e.g. spaCy tokens) it gives the
+// field of every record: `the lemma of tokens`.
+fn field_of(record: &TaleaValue, name: &str) -> std::result::Result<TaleaValue, String> {
+    match record {
+        TaleaValue::Map(fields) => match fields.iter().find(|(key, _)| key == name) {
+            Some((_, value)) => Ok(value.clone()),
+            None => Err(match suggest(name, fields.iter().map(|(key, _)| key.as_str())) {
+                Some(similar) => format!("The record has no field '{}'. Did you mean '{}'?", name, similar),
+                None => {
+                    let keys: Vec<&str> = fields.iter().map(|(key, _)| key.as_str()).collect();
+                    format!("The record has no field '{}'. Its fields are: {}.", name, keys.join(", "))
+                }
+            }),
+        },
+        TaleaValue::List(items) if items.iter().all(|item| matches!(item, TaleaValue::Map(_))) => {
+            items.iter().map(|item| field_of(item, name)).collect::<std::result::Result<Vec<_>, _>>().map(TaleaValue::List)
+        }
+        // The field of every row: a whole column.
+        TaleaValue::Table(table) => table.column(name).map(TaleaValue::List),
+        other => Err(format!("Only records have fields, but '{}' was looked up on {}.", name, other.type_name())),
+    }
+}
+
+// `top 20 of freq`: the rows with the highest count (or, for a list, the
+// largest items). Ties keep their existing order, so a frequency list's
+// alphabetical tie order carries through.
+fn top_of(collection: &TaleaValue, from_bottom: bool, count: &TaleaValue) -> std::result::Result<TaleaValue, String> {
+    let what = if from_bottom { "bottom" } else { "top" };
+    let count = whole_number(count, what)?;
+    let mut cells = CellOrder::default();
+    let mut order = |a: &TaleaValue, b: &TaleaValue| {
+        let ordering = cells.compare(a, b);
+        if from_bottom { ordering } else { ordering.reverse() }
+    };
+    match collection {
+        TaleaValue::Table(table) => {
+            let key = table.ranking_column()
+                .ok_or_else(|| format!("`{}` needs a table with a `count` column or another column of numbers.", what))?;
+            let index = table.column_index(key)?;
+            let mut rows = table.rows.clone();
+            rows.sort_by(|a, b| order(&a[index], &b[index]));
+            rows.truncate(count);
+            Ok(TaleaValue::Table(table.with_rows(rows)))
+        }
+        TaleaValue::List(items) => {
+            let mut items = items.clone();
+            items.sort_by(|a, b| order(a, b));
+            items.truncate(count);
+            Ok(TaleaValue::List(items))
+        }
+        other => Err(format!("`{}` works on tables and lists, but got {}.", what, other.type_name())),
+    }
+}
+
+// `item 3 of words`: items are counted from 1, as people count them.
+fn item_of(collection: &TaleaValue, index: &TaleaValue) -> std::result::Result<TaleaValue, String> {
+    let items = indexable_items(collection, "item")?;
+    let position = whole_number(index, "item")?;
+    if position == 0 { return Err("Items are counted from 1, so the first one is `item 1 of ...`.".to_string()); }
+    items.get(position - 1).cloned()
+        .ok_or_else(|| format!("There is no item {}: {} only has {} item(s).", position, collection.type_name(), items.len()))
+}
+
+// `first 10 of words` keeps the kind of collection (a slice of text is text).
+// Asking for more items than there are simply gives all of them.
+fn slice_of(collection: &TaleaValue, end: SliceEnd, count: Option<&TaleaValue>) -> std::result::Result<TaleaValue, String> {
+    let what = if end == SliceEnd::First { "first" } else { "last" };
+    let items = indexable_items(collection, what)?;
+    let Some(count) = count else {
+        let item = if end == SliceEnd::First { items.first() } else { items.last() };
+        return item.cloned().ok_or_else(|| format!("There is no {} item: {} is empty.", what, collection.type_name()));
+    };
+    let count = whole_number(count, what)?.min(items.len());
+    let range = if end == SliceEnd::First { 0..count } else { items.len() - count..items.len() };
+    Ok(match collection {
+        TaleaValue::String(s) => TaleaValue::String(s.chars().skip(range.start).take(range.len()).collect()),
+        TaleaValue::Tuple(_) => TaleaValue::Tuple(items[range].to_vec()),
+        TaleaValue::Table(table) => TaleaValue::Table(table.with_rows(table.rows[range].to_vec())),
+        _ => TaleaValue::List(items[range].to_vec()),
+    })
+}
+
+// Numbers compare by value whatever their type (so `1 = 1.0`), text compares
+// alphabetically, and anything can be tested for (in)equality.
+fn compare_values(op: &ComparisonOp, left: &TaleaValue, right: &TaleaValue) -> std::result::Result<bool, String> {
+    if let ComparisonOp::Equal | ComparisonOp::NotEqual = op {
+        let equal = values_equal(left, right);
+        return Ok(if *op == ComparisonOp::Equal { equal } else { !equal });
+    }
+    let ordering = match (left, right) {
+        (TaleaValue::Number(a), TaleaValue::Number(b)) => a.cmp(b),
+        (TaleaValue::String(a), TaleaValue::String(b)) => a.cmp(b),
+        _ => match (left.as_float(), right.as_float()) {
+            (Some(a), Some(b)) => a.partial_cmp(&b).ok_or_else(|| "Cannot compare NaN.".to_string())?,
+            _ => return Err(format!("Cannot compare {} with {}.", left.type_name(), right.type_name())),
+        },
+    };
+    Ok(match op {
+        ComparisonOp::Less => ordering.is_lt(),
+        ComparisonOp::LessEqual => ordering.is_le(),
+        ComparisonOp::Greater => ordering.is_gt(),
+        ComparisonOp::GreaterEqual => ordering.is_ge(),
+        ComparisonOp::Equal | ComparisonOp::NotEqual => unreachable!(),
+    })
+}
+
+fn values_equal(left: &TaleaValue, right: &TaleaValue) -> bool {
+    match (left, right) {
+        (TaleaValue::String(a), TaleaValue::String(b)) => a == b,
+        (TaleaValue::Boolean(a), TaleaValue::Boolean(b)) => a == b,
+        (TaleaValue::Number(a), TaleaValue::Number(b)) => a == b,
+        (TaleaValue::List(a), TaleaValue::List(b)) | (TaleaValue::Tuple(a), TaleaValue::Tuple(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(x, y)| values_equal(x, y))
+        }
+        (TaleaValue::Map(a), TaleaValue::Map(b)) => {
+            a.len() == b.len() && a.iter().all(|(key, x)| b.iter().any(|(k, y)| k == key && values_equal(x, y)))
+        }
+        (TaleaValue::Table(a), TaleaValue::Table(b)) => a == b,
+        (TaleaValue::Unit(a), TaleaValue::Unit(b)) => a == b,
+        (TaleaValue::Null, TaleaValue::Null) => true,
+        _ => matches!((left.as_float(), right.as_float()), (Some(a), Some(b)) if a == b),
+    }
+}
+
+// Floats print with exactly `precision` decimals when one is set. Otherwise they
+// use the shortest form that round-trips, capped at 6 decimals, and always keep
+// one decimal so `2.0` still reads as a float.
+fn format_float(f: f64, precision: Option<usize>) -> String {
+    if f.is_nan() { return "NaN".to_string(); }
+    if f.is_infinite() { return if f > 0.0 { "infinity".to_string() } else { "-infinity".to_string() }; }
+    if let Some(p) = precision { return format!("{:.*}", p, f); }
+    let rounded = format!("{:.6}", f);
+    let trimmed = rounded.trim_end_matches('0');
+    if trimmed.ends_with('.') { format!("{}0", trimmed) } else { trimmed.to_string() }
+}
+
 impl TaleaValue {
     fn as_string(&self) -> Option<String> { if let TaleaValue::String(s) = self { Some(s.clone()) } else { None } }
-}
-impl ToString for TaleaValue {
-    fn to_string(&self) -> String {
+    fn as_float(&self) -> Option<f64> { match self { TaleaValue::Number(n) => Some(*n as f64), TaleaValue::Float(f) => Some(*f), _ => None } }
+    fn is_numeric(&self) -> bool { matches!(self, TaleaValue::Number(_) | TaleaValue::Float(_)) }
+
+    // Used by `and`, `or` and `not`: empty text, empty lists, zero and null count as false.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            TaleaValue::Boolean(b) => *b,
+            TaleaValue::Number(n) => *n != 0,
+            TaleaValue::Float(f) => *f != 0.0,
+            TaleaValue::String(s) => !s.is_empty(),
+            TaleaValue::List(l) | TaleaValue::Tuple(l) => !l.is_empty(),
+            TaleaValue::Map(fields) => !fields.is_empty(),
+            TaleaValue::Table(table) => !table.rows.is_empty(),
+            TaleaValue::Unit(_) | TaleaValue::Procedure(_) => true,
+            TaleaValue::Null => false,
+        }
+    }
+
+    // A plain-English name for error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            TaleaValue::String(_) => "text",
+            TaleaValue::Number(_) | TaleaValue::Float(_) => "a number",
+            TaleaValue::Boolean(_) => "true/false",
+            TaleaValue::List(_) => "a list",
+            TaleaValue::Tuple(_) => "a tuple",
+            TaleaValue::Map(_) => "a record",
+            TaleaValue::Table(_) => "a table",
+            TaleaValue::Unit(_) => "a unit",
+            TaleaValue::Procedure(_) => "a procedure",
+            TaleaValue::Null => "null",
+        }
+    }
+
+    // Text shown by `print` and written by `save`. `precision` is the number of
+    // decimals for floats, as set with `set precision to ...`.
+    pub fn display(&self, precision: Option<usize>) -> String {
         match self {
             TaleaValue::String(s) => s.clone(),
             TaleaValue::Number(n) => n.to_string(),
+            TaleaValue::Float(f) => format_float(*f, precision),
+            TaleaValue::Boolean(b) => b.to_string(),
             TaleaValue::List(l) => {
-                let items: Vec<String> = l.iter().map(|val| val.to_string_for_list()).collect();
+                let items: Vec<String> = l.iter().map(|val| val.display_in_list(precision)).collect();
                 format!("[List with {} items]:\n[{}]", l.len(), items.join(", "))
             },
             TaleaValue::Tuple(t) => {
-                let items: Vec<String> = t.iter().map(|val| val.to_string_for_list()).collect();
+                let items: Vec<String> = t.iter().map(|val| val.display_in_list(precision)).collect();
                 format!("({})", items.join(", "))
             },
+            TaleaValue::Table(table) => table.render(precision),
+            // One field per line, with the values lined up:
+            //   [Record with 2 fields]:
+            //   name   Reed
+            //   label  PERSON
+            TaleaValue::Map(fields) => {
+                let width = fields.iter().map(|(key, _)| key.chars().count()).max().unwrap_or(0);
+                let mut out = format!("[Record with {} fields]:", fields.len());
+                for (key, val) in fields {
+                    let padding = " ".repeat(width - key.chars().count());
+                    let val = match val { TaleaValue::String(s) => s.clone(), _ => val.display_in_list(precision) };
+                    out.push_str(&format!("\n{}{}  {}", key, padding, val));
+                }
+                out
+            },
             TaleaValue::Unit(t) => format!("Unit: {:?}", t),
+            TaleaValue::Procedure(p) => format!("<procedure {}>", p.name),
             TaleaValue::Null => "null".to_string(),
         }
     }
-}
-trait ToStringForList { fn to_string_for_list(&self) -> String; }
-impl ToStringForList for TaleaValue {
-    fn to_string_for_list(&self) -> String {
+
+    pub(crate) fn display_in_list(&self, precision: Option<usize>) -> String {
         match self {
             TaleaValue::String(s) => format!("'{}'", s),
-            _ => self.to_string(),
+            // Nested lists and records are shown inline, without a "[... with N ...]" header.
+            TaleaValue::Map(fields) => {
+                let fields: Vec<String> = fields.iter().map(|(key, val)| format!("{}: {}", key, val.display_in_list(precision))).collect();
+                format!("{{{}}}", fields.join(", "))
+            },
+            TaleaValue::List(l) => {
+                let items: Vec<String> = l.iter().map(|val| val.display_in_list(precision)).collect();
+                format!("[{}]", items.join(", "))
+            },
+            TaleaValue::Table(table) => format!("<table: {} rows × {} columns>", table.rows.len(), table.columns.len()),
+            _ => self.display(precision),
         }
     }
-}
\ No newline at end of file
+}
+impl ToString for TaleaValue {
+    fn to_string(&self) -> String {
+        self.display(None)
+    }
+}
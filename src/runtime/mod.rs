@@ -1,32 +1,161 @@
 // src/runtime/mod.rs
 
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use crate::ast::Statement;
+use crate::diagnostics::Spanned;
 use crate::lexer::Token;
 
+pub mod cleaning;
+pub mod corpus;
 pub mod interpreter;
+pub mod patterns;
+pub mod stopwords;
+pub mod table;
+
+use table::Table;
 
 #[derive(Debug, Clone)]
 pub enum TaleaValue {
     String(String),
     Number(i64),
+    Float(f64),
+    Boolean(bool),
     List(Vec<TaleaValue>),
     Tuple(Vec<TaleaValue>),
+    Map(Vec<(String, TaleaValue)>), // A record: named fields, kept in the order they were written
+    Table(Table),
     Unit(Token),
+    Procedure(Rc<Procedure>),
     Null,
 }
 
+// A user-defined procedure, stored in the environment like any other value.
+// It keeps the frame it was defined in, so its body sees the variables that
+// were visible at the definition rather than those of whoever calls it.
+pub struct Procedure {
+    pub name: String,
+    pub parameters: Vec<String>,
+    pub body: Rc<Vec<Spanned<Statement>>>,
+    pub closure: Frame,
+}
+
+// The closure is left out: it usually contains the procedure itself.
+impl fmt::Debug for Procedure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Procedure").field("name", &self.name).field("parameters", &self.parameters).finish()
+    }
+}
+
+pub type Frame = Rc<RefCell<Scope>>;
+
+// One level of variables. Each loop iteration and each procedure call gets a
+// scope whose parent is the scope it was written in. `if` branches share the
+// surrounding scope, so a value chosen in either branch is still there after `end`.
+#[derive(Default)]
+pub struct Scope {
+    values: HashMap<String, TaleaValue>,
+    parent: Option<Frame>,
+    is_procedure: bool, // The outermost scope of a procedure call
+}
+
+// Variables live in a chain of scopes, from the innermost one currently
+// running up to the global scope.
+//
+// - `define` always creates the variable in the innermost scope, shadowing any
+//   variable of the same name further out until that scope ends.
+// - `assign` (`set x to ...`) changes the nearest existing variable, wherever it
+//   lives, and fails if there is none.
+// - `store` is used for the `as x` destination of commands: it changes an
+//   existing variable of the current procedure (or of the script, outside any
+//   procedure), and otherwise creates a new one in the innermost scope. So
+//   `add 1 to total as total` in a loop updates the outer `total`, while a
+//   procedure never overwrites a global by accident.
 pub struct Environment {
-    store: HashMap<String, TaleaValue>,
+    current: Frame,
 }
 
 impl Environment {
     pub fn new() -> Self {
-        Environment { store: HashMap::new() }
+        Environment { current: Rc::new(RefCell::new(Scope::default())) }
     }
     pub fn define(&mut self, name: String, value: TaleaValue) {
-        self.store.insert(name, value);
+        self.current.borrow_mut().values.insert(name, value);
+    }
+    pub fn assign(&mut self, name: &str, value: TaleaValue) -> Result<(), String> {
+        let mut frame = Some(Rc::clone(&self.current));
+        while let Some(scope) = frame {
+            if let Some(slot) = scope.borrow_mut().values.get_mut(name) {
+                *slot = value;
+                return Ok(());
+            }
+            frame = scope.borrow().parent.clone();
+        }
+        Err(format!("Cannot set '{}' because it has not been defined yet. Use `define {} as ...` first.", name, name))
+    }
+    pub fn store(&mut self, name: String, value: TaleaValue) {
+        let mut frame = Some(Rc::clone(&self.current));
+        while let Some(scope) = frame {
+            if let Some(slot) = scope.borrow_mut().values.get_mut(&name) {
+                *slot = value;
+                return;
+            }
+            if scope.borrow().is_procedure { break; }
+            frame = scope.borrow().parent.clone();
+        }
+        self.define(name, value);
     }
     pub fn get(&self, name: &str) -> Option<TaleaValue> {
-        self.store.get(name).cloned()
+        let mut frame = Some(Rc::clone(&self.current));
+        while let Some(scope) = frame {
+            if let Some(value) = scope.borrow().values.get(name) {
+                return Some(value.clone());
+            }
+            frame = scope.borrow().parent.clone();
+        }
+        None
+    }
+    // Every visible variable with the depth of its scope (0 is the innermost),
+    // innermost first. Shadowed variables are listed too, at their own depth.
+    pub fn variables(&self) -> Vec<(usize, String, TaleaValue)> {
+        let mut variables = Vec::new();
+        let mut frame = Some(Rc::clone(&self.current));
+        let mut depth = 0;
+        while let Some(scope) = frame {
+            let mut names: Vec<_> = scope.borrow().values.iter().map(|(name, value)| (depth, name.clone(), value.clone())).collect();
+            names.sort_by(|a, b| a.1.cmp(&b.1));
+            variables.extend(names);
+            frame = scope.borrow().parent.clone();
+            depth += 1;
+        }
+        variables
+    }
+    pub fn names(&self) -> Vec<String> {
+        self.variables().into_iter().map(|(_, name, _)| name).collect()
+    }
+    // The innermost scope, captured by procedures when they are defined.
+    pub fn current_frame(&self) -> Frame {
+        Rc::clone(&self.current)
+    }
+    // Starts a block scope inside the current one.
+    pub fn push_scope(&mut self) {
+        let parent = Rc::clone(&self.current);
+        self.current = Rc::new(RefCell::new(Scope { parent: Some(parent), ..Scope::default() }));
     }
-}
\ No newline at end of file
+    // Starts a procedure call: a new scope inside the procedure's closure.
+    // Returns the caller's scope, to be handed back to `restore` afterwards.
+    pub fn enter_procedure(&mut self, closure: &Frame) -> Frame {
+        let scope = Scope { parent: Some(Rc::clone(closure)), is_procedure: true, ..Scope::default() };
+        std::mem::replace(&mut self.current, Rc::new(RefCell::new(scope)))
+    }
+    pub fn restore(&mut self, frame: Frame) {
+        self.current = frame;
+    }
+    // Ends the innermost block scope. The global scope is never popped.
+    pub fn pop_scope(&mut self) {
+        let parent = self.current.borrow().parent.clone();
+        if let Some(parent) = parent { self.current = parent; }
+    }
+}
@@ -0,0 +1,134 @@
+// src/runtime/patterns.rs
+
+// Regular-expression search, using Rust's `regex` engine (no look-around or
+// backreferences, but linear time on any input).
+
+use regex::{NoExpand, Regex, RegexBuilder};
+
+use crate::runtime::table::Table;
+use crate::runtime::TaleaValue;
+
+// Compiles `pattern`, turning the engine's multi-line error report into one
+// sentence, e.g. `The pattern "colou?(r" is not valid: unclosed group (at character 7).`
+pub fn compile(pattern: &str, ignore_case: bool) -> Result<Regex, String> {
+    RegexBuilder::new(pattern).case_insensitive(ignore_case).build().map_err(|error| match error {
+        regex::Error::Syntax(report) => {
+            let reason = report.lines().find_map(|line| line.strip_prefix("error: ")).unwrap_or("it could not be read");
+            // The report repeats the pattern indented by four spaces, with a `^` under the problem.
+            let position = report.lines().find(|line| !line.trim().is_empty() && line.trim().chars().all(|c| c == '^'))
+                .map(|line| line.chars().take_while(|c| *c == ' ').count().saturating_sub(3));
+            match position {
+                Some(position) if !pattern.contains('\n') => format!("The pattern \"{}\" is not valid: {} (at character {}).", pattern, reason, position),
+                _ => format!("The pattern \"{}\" is not valid: {}.", pattern, reason),
+            }
+        }
+        regex::Error::CompiledTooBig(_) => format!("The pattern \"{}\" is too large to search with.", pattern),
+        other => format!("The pattern \"{}\" is not valid: {}.", pattern, other),
+    })
+}
+
+// One row per match, with
+//   match                the matched text
+//   start, end           character offsets of the match, counting from 0 (`end` is just past it)
+//   byte_start, byte_end the same in bytes of UTF-8
+//   and one column per capture group: its name if it has one, otherwise group1, group2, ...
+//   (empty if the group took no part in the match). A group name that is
+//   already taken by one of the columns above, or by another group, is an error.
+// A list is searched item by item and a record field by field; an `item` or
+// `document` column then says where each match was found.
+pub fn matches_table(source: &TaleaValue, regex: &Regex) -> Result<Table, String> {
+    let (label, texts): (Option<&str>, Vec<(TaleaValue, String)>) = match source {
+        TaleaValue::String(text) => (None, vec![(TaleaValue::Null, text.clone())]),
+        TaleaValue::List(items) | TaleaValue::Tuple(items) => (Some("item"), items.iter().enumerate()
+            .map(|(i, item)| text_of(item).map(|text| (TaleaValue::Number(i as i64 + 1), text)))
+            .collect::<Result<_, _>>()?),
+        TaleaValue::Map(fields) => (Some("document"), fields.iter()
+            .map(|(name, text)| Ok((TaleaValue::String(name.clone()), text_of(text)?)))
+            .collect::<Result<_, String>>()?),
+        other => return Err(format!("`find` searches text, a list or a record of texts, but got {}.", other.type_name())),
+    };
+
+    let mut names: Vec<String> = label.into_iter().chain(["match", "start", "end", "byte_start", "byte_end"]).map(str::to_string).collect();
+    for (i, name) in regex.capture_names().enumerate().skip(1) {
+        let name = name.map_or_else(|| format!("group{}", i), str::to_string);
+        if names.contains(&name) {
+            return Err(format!("The pattern's group {} is called '{}', which is already a column of the results. Give the group another name.", i, name));
+        }
+        names.push(name);
+    }
+    let mut rows = Vec::new();
+    for (place, text) in &texts {
+        for captures in regex.captures_iter(text) {
+            let whole = captures.get(0).expect("group 0 is always the whole match");
+            let start = text[..whole.start()].chars().count();
+            let mut row = Vec::new();
+            if label.is_some() { row.push(place.clone()); }
+            row.extend([
+                TaleaValue::String(whole.as_str().to_string()),
+                TaleaValue::Number(start as i64),
+                TaleaValue::Number((start + whole.as_str().chars().count()) as i64),
+                TaleaValue::Number(whole.start() as i64),
+                TaleaValue::Number(whole.end() as i64),
+            ]);
+            row.extend((1..captures.len()).map(|i| captures.get(i).map_or(TaleaValue::Null, |group| TaleaValue::String(group.as_str().to_string()))));
+            rows.push(row);
+        }
+    }
+    Table::new(names, rows)
+}
+
+// Replaces every match in a text, or in each text of a list (other items are
+// left as they are), and says how many replacements were made. Unless
+// `literal`, the replacement can refer to groups as `$1` or `${name}`.
+pub fn replace_in(value: &TaleaValue, regex: &Regex, replacement: &str, literal: bool) -> Result<(TaleaValue, usize), String> {
+    match value {
+        TaleaValue::String(text) => {
+            let count = regex.find_iter(text).count();
+            let replaced = if literal { regex.replace_all(text, NoExpand(replacement)) } else { regex.replace_all(text, numbered_groups_braced(replacement).as_str()) };
+            Ok((TaleaValue::String(replaced.into_owned()), count))
+        }
+        TaleaValue::List(items) | TaleaValue::Tuple(items) => {
+            let mut total = 0;
+            let mut replaced = Vec::with_capacity(items.len());
+            for item in items {
+                let (item, count) = match item {
+                    TaleaValue::String(_) | TaleaValue::List(_) | TaleaValue::Tuple(_) => replace_in(item, regex, replacement, literal)?,
+                    other => (other.clone(), 0),
+                };
+                replaced.push(item);
+                total += count;
+            }
+            let replaced = if matches!(value, TaleaValue::Tuple(_)) { TaleaValue::Tuple(replaced) } else { TaleaValue::List(replaced) };
+            Ok((replaced, total))
+        }
+        other => Err(format!("`replace` works on text and lists of text, but got {}.", other.type_name())),
+    }
+}
+
+// `$1ed` means group 1 followed by "ed", as people expect, rather than a
+// group named `1ed` (the engine's reading): numbered references end at
+// their last digit. `$$` stays a plain dollar sign.
+fn numbered_groups_braced(replacement: &str) -> String {
+    let mut out = String::with_capacity(replacement.len());
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('$', Some('$')) => { out.push_str("$$"); chars.next(); }
+            ('$', Some(d)) if d.is_ascii_digit() => {
+                out.push_str("${");
+                while let Some(d) = chars.next_if(char::is_ascii_digit) { out.push(d); }
+                out.push('}');
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn text_of(value: &TaleaValue) -> Result<String, String> {
+    match value {
+        TaleaValue::String(text) => Ok(text.clone()),
+        TaleaValue::Number(_) | TaleaValue::Float(_) | TaleaValue::Boolean(_) => Ok(value.display(None)),
+        other => Err(format!("`find` can only search text, but found {}.", other.type_name())),
+    }
+}
@@ -0,0 +1,162 @@
+// src/runtime/stopwords.rs
+
+// Stopwords: the very frequent function words (articles, pronouns,
+// prepositions, auxiliaries) that are usually left out of content analyses.
+// Lists are lower case; words are looked up in lower case too.
+
+use std::collections::HashSet;
+
+use unicode_normalization::UnicodeNormalization;
+
+// The built-in lists, by the names `remove stopwords in <language>` accepts.
+pub const LANGUAGES: &[&str] = &["english", "german", "french", "spanish", "italian", "latin", "greek"];
+
+pub const ENGLISH: &[&str] = &[
+    "a", "about", "above", "after", "again", "against", "all", "am", "an", "and", "any", "are", "as", "at",
+    "be", "because", "been", "before", "being", "below", "between", "both", "but", "by",
+    "can", "could", "did", "do", "does", "doing", "down", "during", "each", "few", "for", "from", "further",
+    "had", "has", "have", "having", "he", "her", "here", "hers", "herself", "him", "himself", "his", "how",
+    "i", "if", "in", "into", "is", "it", "its", "itself", "just", "me", "more", "most", "my", "myself",
+    "no", "nor", "not", "now", "of", "off", "on", "once", "only", "or", "other", "ought", "our", "ours", "ourselves", "out", "over", "own",
+    "same", "she", "should", "so", "some", "such", "than", "that", "the", "their", "theirs", "them", "themselves", "then", "there",
+    "these", "they", "this", "those", "through", "to", "too", "under", "until", "up", "very",
+    "was", "we", "were", "what", "when", "where", "which", "while", "who", "whom", "why", "will", "with", "would",
+    "you", "your", "yours", "yourself", "yourselves",
+];
+
+pub const GERMAN: &[&str] = &[
+    "aber", "alle", "allem", "allen", "aller", "alles", "als", "also", "am", "an", "ander", "andere",
+    "anderem", "anderen", "anderer", "anderes", "auch", "auf", "aus", "bei", "bin", "bis", "bist", "da",
+    "damit", "dann", "das", "dass", "dein", "deine", "dem", "den", "denn", "der", "des", "dich", "die",
+    "dies", "diese", "diesem", "diesen", "dieser", "dieses", "dir", "doch", "dort", "du", "durch", "ein",
+    "eine", "einem", "einen", "einer", "eines", "er", "es", "etwas", "euch", "euer", "eure", "für", "gegen",
+    "gewesen", "hab", "habe", "haben", "hat", "hatte", "hatten", "hier", "hin", "hinter", "ich", "ihm", "ihn",
+    "ihnen", "ihr", "ihre", "ihrem", "ihren", "ihrer", "im", "in", "ins", "ist", "ja", "jede", "jedem",
+    "jeden", "jeder", "jedes", "jener", "jetzt", "kann", "kein", "keine", "können", "man", "manche", "mein",
+    "meine", "mich", "mir", "mit", "muss", "nach", "nicht", "nichts", "noch", "nun", "nur", "ob", "oder",
+    "ohne", "sehr", "sein", "seine", "seinem", "seinen", "seiner", "sich", "sie", "sind", "so", "solche",
+    "soll", "sondern", "sonst", "über", "um", "und", "uns", "unser", "unter", "viel", "vom", "von", "vor",
+    "war", "waren", "warst", "was", "weg", "weil", "weiter", "welche", "wenn", "wer", "werde", "werden",
+    "wie", "wieder", "will", "wir", "wird", "wo", "wollen", "zu", "zum", "zur", "zwar", "zwischen",
+];
+
+pub const FRENCH: &[&str] = &[
+    "a", "ai", "aie", "as", "au", "aura", "aux", "avait", "avaient", "avec", "avez", "avons", "c", "car",
+    "ce", "ceci", "cela", "ces", "cet", "cette", "comme", "d", "dans", "de", "des", "donc", "du", "elle",
+    "elles", "en", "es", "est", "et", "étaient", "était", "été", "être", "eu", "eux", "il", "ils", "j", "je",
+    "l", "la", "le", "les", "leur", "leurs", "lui", "m", "ma", "mais", "me", "même", "mes", "moi", "mon", "n",
+    "ne", "ni", "nos", "notre", "nous", "on", "ont", "ou", "où", "par", "pas", "plus", "pour", "qu", "quand",
+    "que", "qui", "s", "sa", "sans", "se", "sera", "serait", "ses", "si", "son", "sont", "sur", "t", "ta",
+    "te", "tes", "toi", "ton", "tous", "tout", "toute", "toutes", "très", "tu", "un", "une", "vos", "votre",
+    "vous", "y", "à", "ça",
+];
+
+pub const SPANISH: &[&str] = &[
+    "a", "al", "algo", "algunas", "algunos", "ante", "antes", "como", "con", "contra", "cual", "cuando", "de",
+    "del", "desde", "donde", "durante", "e", "el", "él", "ella", "ellas", "ellos", "en", "entre", "era",
+    "eran", "es", "esa", "esas", "ese", "eso", "esos", "esta", "está", "estaba", "estado", "estas", "este",
+    "esto", "estos", "fue", "fueron", "ha", "había", "han", "hasta", "hay", "la", "las", "le", "les", "lo",
+    "los", "más", "me", "mi", "mis", "mucho", "muy", "nada", "ni", "no", "nos", "nosotros", "o", "os", "otra",
+    "otro", "para", "pero", "poco", "por", "porque", "que", "qué", "quien", "se", "sea", "ser", "si", "sí",
+    "sin", "sobre", "son", "su", "sus", "también", "tan", "te", "tiene", "todo", "todos", "tu", "tú", "tus",
+    "un", "una", "uno", "unos", "y", "ya", "yo",
+];
+
+pub const ITALIAN: &[&str] = &[
+    "a", "ad", "agli", "ai", "al", "alla", "alle", "allo", "anche", "avere", "aveva", "c", "che", "chi", "ci",
+    "come", "con", "contro", "cui", "da", "dal", "dalla", "dalle", "degli", "dei", "del", "della", "delle",
+    "dello", "di", "dove", "e", "è", "ed", "era", "erano", "essere", "fa", "gli", "ha", "hanno", "ho", "i",
+    "il", "in", "io", "la", "le", "lei", "lo", "loro", "lui", "ma", "mi", "mia", "mie", "miei", "mio", "ne",
+    "nei", "nel", "nella", "nelle", "noi", "non", "nostro", "o", "per", "perché", "più", "quale", "quando",
+    "quella", "quello", "questa", "questo", "se", "sei", "si", "sia", "sono", "su", "sua", "sue", "sul",
+    "sulla", "suo", "suoi", "ti", "tra", "tu", "tuo", "un", "una", "uno", "vi", "voi",
+];
+
+// Classical spellings with both u and v, as editions differ.
+pub const LATIN: &[&str] = &[
+    "a", "ab", "ac", "ad", "adhuc", "an", "ante", "apud", "at", "atque", "aut", "autem", "cum", "cur", "de",
+    "deinde", "dum", "enim", "ergo", "et", "etiam", "etsi", "ex", "e", "haud", "iam", "igitur", "in", "infra",
+    "inter", "ita", "magis", "modo", "mox", "nam", "ne", "nec", "neque", "nisi", "non", "o", "ob", "per",
+    "post", "pro", "quam", "quare", "quia", "quidem", "quoniam", "sed", "si", "sic", "sine", "siue", "sive",
+    "sub", "super", "tam", "tamen", "trans", "tum", "ubi", "uel", "vel", "uero", "vero", "ut", "ego", "mei",
+    "mihi", "me", "nos", "nostri", "nostrum", "nobis", "tu", "tui", "tibi", "te", "vos", "uos", "vestri",
+    "uestri", "vobis", "uobis", "sui", "sibi", "se", "hic", "haec", "hoc", "huius", "huic", "hunc", "hanc",
+    "hac", "hi", "hae", "horum", "harum", "his", "hos", "has", "is", "ea", "id", "eius", "ei", "eum", "eam",
+    "eo", "ii", "eae", "eorum", "earum", "eis", "iis", "eos", "eas", "ille", "illa", "illud", "illius",
+    "illi", "illum", "illam", "illo", "illae", "illorum", "illarum", "illis", "illos", "illas", "ipse",
+    "ipsa", "ipsum", "ipsius", "ipsi", "ipsam", "ipso", "qui", "quae", "quod", "cuius", "cui", "quem", "qua",
+    "quo", "quorum", "quarum", "quibus", "quos", "quas", "quis", "quid", "sum", "es", "est", "sumus", "estis",
+    "sunt", "eram", "erat", "erant", "esse", "fuit", "fui", "esset", "meus", "mea", "meum", "tuus", "tua",
+    "tuum", "suus", "sua", "suum", "noster", "nostra", "uester", "vester",
+];
+
+// Polytonic, with acute accents; `builtin` adds the grave forms these words
+// take before another word (καί → καὶ).
+pub const ANCIENT_GREEK: &[&str] = &[
+    "ὁ", "ἡ", "τό", "οἱ", "αἱ", "τά", "τοῦ", "τῆς", "τῷ", "τῇ", "τόν", "τήν", "τῶν", "τοῖς", "ταῖς", "τούς",
+    "τάς", "καί", "δέ", "τε", "γάρ", "μέν", "οὖν", "ἀλλά", "ἄρα", "γε", "δή", "μήν", "καίτοι", "οὔτε", "μήτε",
+    "ἤ", "ἠδέ", "ἐν", "εἰς", "ἐς", "ἐκ", "ἐξ", "ἀπό", "ἐπί", "πρός", "περί", "παρά", "κατά", "μετά", "διά",
+    "ὑπό", "ὑπέρ", "ἀντί", "σύν", "ἀμφί", "ὡς", "ὅτι", "εἰ", "ἐάν", "ἄν", "μή", "οὐ", "οὐκ", "οὐχ", "οὐδέ",
+    "μηδέ", "ὅπως", "ἐπεί", "ἵνα", "ὥστε", "ἔτι", "οὕτως", "αὐτός", "αὐτή", "αὐτό", "αὐτοῦ", "αὐτῆς", "αὐτῷ",
+    "αὐτῇ", "αὐτόν", "αὐτήν", "αὐτῶν", "αὐτοῖς", "αὐταῖς", "αὐτούς", "αὐτάς", "ἐγώ", "μου", "ἐμοῦ", "μοι",
+    "ἐμοί", "με", "ἐμέ", "σύ", "σου", "σοῦ", "σοι", "σοί", "σε", "σέ", "ἡμεῖς", "ἡμῶν", "ἡμῖν", "ἡμᾶς",
+    "ὑμεῖς", "ὑμῶν", "ὑμῖν", "ὑμᾶς", "οὗτος", "αὕτη", "τοῦτο", "τούτου", "ταύτης", "τούτῳ", "τοῦτον",
+    "ταύτην", "οὗτοι", "αὗται", "ταῦτα", "τούτων", "τούτοις", "τούτους", "ἐκεῖνος", "ἐκείνη", "ἐκεῖνο", "ὅς",
+    "ἥ", "ὅ", "οὗ", "ἧς", "ᾧ", "ᾗ", "ὅν", "ἥν", "οἵ", "αἵ", "ἅ", "ὧν", "οἷς", "αἷς", "οὕς", "ἅς", "ὅστις",
+    "ἥτις", "τις", "τι", "τινός", "τινί", "τινά", "τίς", "τί", "εἰμί", "ἐστί", "ἐστίν", "εἰσί", "εἰσίν", "ἦν",
+    "εἶναι", "ὤν", "οὖσα", "ὄν", "πᾶς", "πᾶσα", "πᾶν", "πάντα", "πάντες", "πάντων",
+];
+
+// A built-in list by language name ("greek" is Ancient Greek).
+pub fn builtin(language: &str) -> Option<HashSet<String>> {
+    let words = match language.to_lowercase().as_str() {
+        "english" => ENGLISH,
+        "german" => GERMAN,
+        "french" => FRENCH,
+        "spanish" => SPANISH,
+        "italian" => ITALIAN,
+        "latin" => LATIN,
+        "greek" | "ancient_greek" => {
+            let mut set = set_of(ANCIENT_GREEK);
+            set.extend(ANCIENT_GREEK.iter().filter_map(|word| with_grave(word)));
+            return Some(set);
+        }
+        _ => return None,
+    };
+    Some(set_of(words))
+}
+
+// A list as a set, for quick lookups. Words are lower-cased and put in NFC,
+// the form `normalize` gives, so accented words match however they were typed.
+pub fn set_of<S: AsRef<str>>(words: &[S]) -> HashSet<String> {
+    words.iter().map(|word| word.as_ref().to_lowercase().nfc().collect()).collect()
+}
+
+// True if `word` is in `list`, ignoring case and any punctuation around it.
+pub fn is_stopword(list: &HashSet<String>, word: &str) -> bool {
+    // Composed first, so a trailing combining accent isn't trimmed as punctuation.
+    let composed: String = word.nfc().collect();
+    list.contains(&composed.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+}
+
+// A stopword file: one or more words per line, separated by spaces or
+// commas. Lines starting with `#` are comments.
+pub fn parse_list(text: &str) -> Vec<String> {
+    text.lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// The word with its last acute accent made grave (καί → καὶ), if it has one
+// on its final vowel.
+fn with_grave(word: &str) -> Option<String> {
+    let decomposed: Vec<char> = word.nfd().collect();
+    let last_vowel = decomposed.iter().rposition(|c| "αεηιουωΑΕΗΙΟΥΩ".contains(*c))?;
+    let accent = last_vowel + 1 + decomposed[last_vowel + 1..].iter().position(|&c| c == '\u{0301}')?;
+    let mut graved = decomposed;
+    graved[accent] = '\u{0300}';
+    Some(graved.into_iter().nfc().collect())
+}
@@ -0,0 +1,335 @@
+// src/runtime/table.rs
+
+// Tables: named columns and rows of cells. Entity lists, frequency lists and
+// per-document metrics are all tables, so they get a value type of their own
+// with proper terminal rendering and CSV/TSV output.
+
+use std::cmp::Ordering;
+
+use feruca::Collator;
+
+use crate::diagnostics::suggest;
+use crate::runtime::TaleaValue;
+
+// Cells longer than this are cut short (with `…`) when a table is printed.
+const MAX_CELL_WIDTH: usize = 40;
+// Only this many rows are printed; the rest are summarized in one line.
+const MAX_PRINTED_ROWS: usize = 20;
+
+// The kind of values a column holds, worked out from its cells. Numeric
+// columns are right-aligned when printed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnType { Text, Integer, Float, Boolean, Mixed, Empty }
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub kind: ColumnType,
+    // Printed right-aligned and, when too long, cut short at the start rather
+    // than the end, e.g. the left context of a concordance line.
+    pub flush_right: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub columns: Vec<Column>,
+    pub rows: Vec<Vec<TaleaValue>>,
+}
+
+impl Table {
+    // Every row must have one cell per column, and no two columns may share a name.
+    pub fn new(names: Vec<String>, rows: Vec<Vec<TaleaValue>>) -> Result<Self, String> {
+        check_unique(&names)?;
+        if let Some((i, row)) = rows.iter().enumerate().find(|(_, row)| row.len() != names.len()) {
+            return Err(format!("Row {} has {} cells, but the table has {} columns.", i + 1, row.len(), names.len()));
+        }
+        let columns = names.into_iter().enumerate()
+            .map(|(i, name)| Column { kind: column_type(rows.iter().map(|row| &row[i])), name, flush_right: false })
+            .collect();
+        Ok(Table { columns, rows })
+    }
+
+    // A list of records becomes one row per record. The columns are every field
+    // that appears, in the order they first appear; missing fields are null.
+    pub fn from_records(records: &[TaleaValue]) -> Result<Self, String> {
+        let mut names: Vec<String> = Vec::new();
+        for record in records {
+            let TaleaValue::Map(fields) = record else { return Err(format!("A table needs every item to be a record, but found {}.", record.type_name())); };
+            for (key, _) in fields {
+                if !names.contains(key) { names.push(key.clone()); }
+            }
+        }
+        let rows = records.iter().map(|record| {
+            let TaleaValue::Map(fields) = record else { unreachable!("checked above") };
+            names.iter().map(|name| fields.iter().find(|(key, _)| key == name).map_or(TaleaValue::Null, |(_, value)| value.clone())).collect()
+        }).collect();
+        Table::new(names, rows)
+    }
+
+    // A list of tuples, such as `("Reed", "PERSON")`, with the given column names.
+    pub fn from_tuples(names: Vec<String>, tuples: &[TaleaValue]) -> Result<Self, String> {
+        let rows = tuples.iter().map(|tuple| match tuple {
+            TaleaValue::Tuple(cells) | TaleaValue::List(cells) => Ok(cells.clone()),
+            other => Ok(vec![other.clone()]),
+        }).collect::<Result<Vec<_>, String>>()?;
+        Table::new(names, rows)
+    }
+
+    // Marks a column as flush right (see `Column`).
+    pub fn flush_right(mut self, name: &str) -> Self {
+        for column in self.columns.iter_mut().filter(|column| column.name == name) { column.flush_right = true; }
+        self
+    }
+
+    pub fn column_names(&self) -> Vec<&str> {
+        self.columns.iter().map(|column| column.name.as_str()).collect()
+    }
+
+    pub fn column_index(&self, name: &str) -> Result<usize, String> {
+        self.columns.iter().position(|column| column.name == name).ok_or_else(|| match suggest(name, self.column_names()) {
+            Some(similar) => format!("The table has no column '{}'. Did you mean '{}'?", name, similar),
+            None => format!("The table has no column '{}'. Its columns are: {}.", name, self.column_names().join(", ")),
+        })
+    }
+
+    // All the values of one column, top to bottom.
+    pub fn column(&self, name: &str) -> Result<Vec<TaleaValue>, String> {
+        let index = self.column_index(name)?;
+        Ok(self.rows.iter().map(|row| row[index].clone()).collect())
+    }
+
+    // One row as a record, so it can be used with `the <column> of row`.
+    pub fn row_record(&self, row: &[TaleaValue]) -> TaleaValue {
+        TaleaValue::Map(self.columns.iter().zip(row).map(|(column, cell)| (column.name.clone(), cell.clone())).collect())
+    }
+
+    pub fn records(&self) -> Vec<TaleaValue> {
+        self.rows.iter().map(|row| self.row_record(row)).collect()
+    }
+
+    // A new table with just these columns, in this order.
+    pub fn select(&self, names: &[String]) -> Result<Table, String> {
+        check_unique(names)?;
+        let indices = names.iter().map(|name| self.column_index(name)).collect::<Result<Vec<_>, _>>()?;
+        let columns = indices.iter().map(|&i| self.columns[i].clone()).collect();
+        let rows = self.rows.iter().map(|row| indices.iter().map(|&i| row[i].clone()).collect()).collect();
+        Ok(Table { columns, rows })
+    }
+
+    // The same columns with a different set of rows (after filtering or sorting).
+    pub fn with_rows(&self, rows: Vec<Vec<TaleaValue>>) -> Table {
+        Table { columns: self.columns.clone(), rows }
+    }
+
+    // Sorts the rows by one column (stably) and puts a `rank` column first,
+    // replacing any existing one. Tied rows share a rank: 1, 2, 2, 4.
+    pub fn ranked_by(&self, key: &str, descending: bool) -> Result<Table, String> {
+        let key_index = self.column_index(key)?;
+        let mut rows = self.rows.clone();
+        let mut order = CellOrder::default();
+        rows.sort_by(|a, b| {
+            let ordering = order.compare(&a[key_index], &b[key_index]);
+            if descending { ordering.reverse() } else { ordering }
+        });
+        let mut ranks: Vec<i64> = Vec::with_capacity(rows.len());
+        for i in 0..rows.len() {
+            let tied = i > 0 && compare_cells(&rows[i - 1][key_index], &rows[i][key_index]) == Ordering::Equal;
+            ranks.push(if tied { ranks[i - 1] } else { i as i64 + 1 });
+        }
+        let kept: Vec<usize> = (0..self.columns.len()).filter(|&i| self.columns[i].name != "rank").collect();
+        let names = std::iter::once("rank".to_string()).chain(kept.iter().map(|&i| self.columns[i].name.clone())).collect();
+        let rows = rows.into_iter().zip(ranks)
+            .map(|(row, rank)| std::iter::once(TaleaValue::Number(rank)).chain(kept.iter().map(|&i| row[i].clone())).collect())
+            .collect();
+        let mut table = Table::new(names, rows)?;
+        // Keep the columns' print settings, e.g. a concordance's flush-right `left`.
+        for (column, &i) in table.columns.iter_mut().skip(1).zip(&kept) {
+            column.flush_right = self.columns[i].flush_right;
+        }
+        Ok(table)
+    }
+
+    // The column that `top`/`bottom` rank by: `count` if there is one,
+    // otherwise the first numeric column.
+    pub fn ranking_column(&self) -> Option<&str> {
+        self.columns.iter().find(|column| column.name == "count")
+            .or_else(|| self.columns.iter().find(|column| column.name != "rank" && matches!(column.kind, ColumnType::Integer | ColumnType::Float)))
+            .map(|column| column.name.as_str())
+    }
+
+    // Draws the table with box characters, e.g.
+    //
+    //   ┌─────────────┬────────┐
+    //   │ text        │ label  │
+    //   ├─────────────┼────────┤
+    //   │ Evelyn Reed │ PERSON │
+    //   └─────────────┴────────┘
+    //   1 row × 2 columns
+    pub fn render(&self, precision: Option<usize>) -> String {
+        let shown = &self.rows[..self.rows.len().min(MAX_PRINTED_ROWS)];
+        let cells: Vec<Vec<String>> = shown.iter()
+            .map(|row| row.iter().zip(&self.columns).map(|(cell, column)| truncate(&cell_text(cell, precision), column.flush_right)).collect())
+            .collect();
+        let headers: Vec<String> = self.columns.iter().map(|column| truncate(&column.name, false)).collect();
+        let widths: Vec<usize> = (0..self.columns.len())
+            .map(|i| cells.iter().map(|row| row[i].chars().count()).chain([headers[i].chars().count()]).max().unwrap_or(0))
+            .collect();
+
+        let border = |left: &str, middle: &str, right: &str| {
+            let segments: Vec<String> = widths.iter().map(|&w| "─".repeat(w + 2)).collect();
+            format!("{}{}{}\n", left, segments.join(middle), right)
+        };
+        let line = |values: &[String], align_numbers: bool| {
+            let padded: Vec<String> = values.iter().zip(&self.columns).zip(&widths).map(|((value, column), &width)| {
+                let padding = " ".repeat(width - value.chars().count());
+                let numeric = matches!(column.kind, ColumnType::Integer | ColumnType::Float);
+                if align_numbers && (numeric || column.flush_right) { format!(" {}{} ", padding, value) } else { format!(" {}{} ", value, padding) }
+            }).collect();
+            format!("│{}│\n", padded.join("│"))
+        };
+
+        let mut out = border("┌", "┬", "┐");
+        out.push_str(&line(&headers, false));
+        out.push_str(&border("├", "┼", "┤"));
+        for row in &cells { out.push_str(&line(row, true)); }
+        out.push_str(&border("└", "┴", "┘"));
+        if self.rows.len() > shown.len() {
+            out.push_str(&format!("… {} more rows not shown\n", self.rows.len() - shown.len()));
+        }
+        out.push_str(&format!("{} {} × {} {}", self.rows.len(), plural(self.rows.len(), "row"), self.columns.len(), plural(self.columns.len(), "column")));
+        out
+    }
+
+    // The table as CSV (`separator` ',') or TSV ('\t'), with a header row.
+    // Cells containing the separator, quotes or line breaks are quoted.
+    pub fn to_delimited(&self, separator: char, precision: Option<usize>) -> String {
+        let escape = |text: String| {
+            if text.contains(separator) || text.contains('"') || text.contains('\n') || text.contains('\r') {
+                format!("\"{}\"", text.replace('"', "\"\""))
+            } else {
+                text
+            }
+        };
+        let separator = separator.to_string();
+        let mut out = self.columns.iter().map(|column| escape(column.name.clone())).collect::<Vec<_>>().join(&separator);
+        out.push('\n');
+        for row in &self.rows {
+            out.push_str(&row.iter().map(|cell| escape(cell_text(cell, precision))).collect::<Vec<_>>().join(&separator));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl PartialEq for Table {
+    fn eq(&self, other: &Self) -> bool {
+        self.column_names() == other.column_names() && self.rows.len() == other.rows.len()
+            && self.rows.iter().zip(&other.rows).all(|(a, b)| a.iter().zip(b).all(|(x, y)| compare_cells(x, y) == Ordering::Equal))
+    }
+}
+
+// The order used for sorting: numbers by value, text in dictionary order,
+// false before true. Numbers come before text, and empty (null) cells always last.
+//
+// Text is compared with the Unicode Collation Algorithm (CLDR root order), so
+// "Émile" sorts between "Elrond" and "Ernie" rather than after "Zola", and
+// differences in case or accents only decide between otherwise equal words.
+#[derive(Default)]
+pub struct CellOrder {
+    collator: Collator,
+}
+
+impl CellOrder {
+    pub fn compare(&mut self, a: &TaleaValue, b: &TaleaValue) -> Ordering {
+        compare_by(a, b, &mut |x, y| self.collator.collate(x, y))
+    }
+}
+
+// Like `CellOrder`, but text is compared code point by code point. Cheaper,
+// and enough to tell whether two cells are equal.
+fn compare_cells(a: &TaleaValue, b: &TaleaValue) -> Ordering {
+    compare_by(a, b, &mut |x, y| x.cmp(y))
+}
+
+fn compare_by(a: &TaleaValue, b: &TaleaValue, compare_text: &mut dyn FnMut(&str, &str) -> Ordering) -> Ordering {
+    match (a, b) {
+        (TaleaValue::Null, TaleaValue::Null) => Ordering::Equal,
+        (TaleaValue::Null, _) => Ordering::Greater,
+        (_, TaleaValue::Null) => Ordering::Less,
+        (TaleaValue::Number(x), TaleaValue::Number(y)) => x.cmp(y),
+        (TaleaValue::Number(_) | TaleaValue::Float(_), TaleaValue::Number(_) | TaleaValue::Float(_)) => {
+            number(a).partial_cmp(&number(b)).unwrap_or(Ordering::Equal)
+        }
+        (TaleaValue::Number(_) | TaleaValue::Float(_), _) => Ordering::Less,
+        (_, TaleaValue::Number(_) | TaleaValue::Float(_)) => Ordering::Greater,
+        (TaleaValue::String(x), TaleaValue::String(y)) => compare_text(x, y),
+        (TaleaValue::Boolean(x), TaleaValue::Boolean(y)) => x.cmp(y),
+        _ => compare_text(&a.display(None), &b.display(None)),
+    }
+}
+
+fn number(value: &TaleaValue) -> f64 {
+    match value {
+        TaleaValue::Number(n) => *n as f64,
+        TaleaValue::Float(f) => *f,
+        _ => f64::NAN,
+    }
+}
+
+// Lookups by name (`select`, `where`, `the x of row`) would only ever see the
+// first of two columns with the same name.
+fn check_unique(names: &[String]) -> Result<(), String> {
+    match names.iter().enumerate().find(|(i, name)| names[..*i].contains(name)) {
+        Some((_, name)) => Err(format!("A table cannot have two columns called '{}'.", name)),
+        None => Ok(()),
+    }
+}
+
+fn column_type<'a>(cells: impl Iterator<Item = &'a TaleaValue>) -> ColumnType {
+    let mut kind = ColumnType::Empty;
+    for cell in cells {
+        let cell_kind = match cell {
+            TaleaValue::Null => continue,
+            TaleaValue::String(_) => ColumnType::Text,
+            TaleaValue::Number(_) => ColumnType::Integer,
+            TaleaValue::Float(_) => ColumnType::Float,
+            TaleaValue::Boolean(_) => ColumnType::Boolean,
+            _ => ColumnType::Mixed,
+        };
+        kind = match (kind, cell_kind) {
+            (ColumnType::Empty, k) => k,
+            (k, c) if k == c => k,
+            // A column of whole numbers with some decimals is still numeric.
+            (ColumnType::Integer, ColumnType::Float) | (ColumnType::Float, ColumnType::Integer) => ColumnType::Float,
+            _ => ColumnType::Mixed,
+        };
+    }
+    kind
+}
+
+// How a cell is written when printed or saved: text without quotes, null as nothing.
+fn cell_text(cell: &TaleaValue, precision: Option<usize>) -> String {
+    match cell {
+        TaleaValue::String(s) => s.clone(),
+        TaleaValue::Null => String::new(),
+        other => other.display_in_list(precision),
+    }
+}
+
+// Keeps a cell on one line and within `MAX_CELL_WIDTH` characters, keeping
+// the end of the text instead of the start if `keep_end` is set.
+fn truncate(text: &str, keep_end: bool) -> String {
+    let one_line = text.replace(['\n', '\r', '\t'], " ");
+    let length = one_line.chars().count();
+    if length <= MAX_CELL_WIDTH { return one_line; }
+    if keep_end {
+        return std::iter::once('…').chain(one_line.chars().skip(length - (MAX_CELL_WIDTH - 1))).collect();
+    }
+    let mut cut: String = one_line.chars().take(MAX_CELL_WIDTH - 1).collect();
+    cut.push('…');
+    cut
+}
+
+fn plural(count: usize, word: &str) -> String {
+    if count == 1 { word.to_string() } else { format!("{}s", word) }
+}
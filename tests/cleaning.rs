@@ -0,0 +1,90 @@
+// tests/cleaning.rs
+//
+// Cleaning steps on texts and word lists, especially the punctuation that
+// has to survive them: apostrophes and hyphens inside words, separators
+// inside numbers.
+
+use std::collections::HashSet;
+
+use talea::ast::CleaningStep;
+use talea::runtime::cleaning::{clean, run_step};
+use talea::runtime::stopwords;
+use talea::runtime::TaleaValue;
+
+fn text(value: TaleaValue) -> String {
+    match value {
+        TaleaValue::String(text) => text,
+        other => panic!("expected text, got {:?}", other),
+    }
+}
+
+fn strip(input: &str) -> String {
+    text(run_step(&TaleaValue::String(input.to_string()), CleaningStep::Punctuation, &HashSet::new()).unwrap())
+}
+
+fn strip_words(items: &[&str]) -> Vec<String> {
+    let list = TaleaValue::List(items.iter().map(|item| TaleaValue::String(item.to_string())).collect());
+    match run_step(&list, CleaningStep::Punctuation, &HashSet::new()).unwrap() {
+        TaleaValue::List(items) => items.into_iter().map(text).collect(),
+        other => panic!("expected a list, got {:?}", other),
+    }
+}
+
+fn cleaned(input: &str, steps: &[CleaningStep]) -> String {
+    text(clean(&TaleaValue::String(input.to_string()), steps, &stopwords::set_of(stopwords::ENGLISH)).unwrap())
+}
+
+#[test]
+fn punctuation_around_words_is_removed() {
+    assert_eq!(strip("Hello, world! (Really?)"), "Hello world Really");
+    assert_eq!(strip("«Bonjour» — dit-il…"), "Bonjour  dit-il");
+}
+
+#[test]
+fn apostrophes_and_hyphens_inside_words_stay() {
+    assert_eq!(strip("don't stop, it's well-known"), "don't stop it's well-known");
+    assert_eq!(strip("l’homme"), "l’homme");
+    // Only between two letters: quotes and dashes at the edges go.
+    assert_eq!(strip("'tis the dogs' -end- of-"), "tis the dogs end of");
+}
+
+#[test]
+fn separators_inside_numbers_stay() {
+    assert_eq!(strip("1,000 people paid 3.5 each."), "1,000 people paid 3.5 each");
+    assert_eq!(strip("Chapters 1, 2, 3."), "Chapters 1 2 3");
+    // A point between a letter and a digit is not a decimal point.
+    assert_eq!(strip("v.2"), "v2");
+}
+
+#[test]
+fn accents_and_combining_marks_stay() {
+    let decomposed = "cafe\u{301}!";
+    assert_eq!(strip(decomposed), "cafe\u{301}");
+    assert_eq!(strip("नमस्ते।"), "नमस्ते");
+}
+
+#[test]
+fn words_left_empty_are_dropped_from_lists() {
+    assert_eq!(strip_words(&["Hello", ",", "world", "!", "don't", "—"]), ["Hello", "world", "don't"]);
+}
+
+#[test]
+fn clean_runs_its_steps_in_a_fixed_order() {
+    use CleaningStep::*;
+    // Punctuation goes before whitespace, so the gap left by " , " is closed
+    // whichever order the steps are listed in.
+    assert_eq!(cleaned("a , b", &[Whitespace, Punctuation]), "a b");
+    assert_eq!(cleaned("a , b", &[Punctuation, Whitespace]), "a b");
+    // Lowercase, then punctuation, then stopwords, then whitespace.
+    assert_eq!(cleaned("The, CAT; and the DOG", &[Whitespace, Stopwords, Punctuation, Lowercase]), "cat dog");
+    // Numbers go after punctuation, so a number's separators are still there
+    // to keep it whole.
+    assert_eq!(cleaned("Paid 1,000.", &[Numbers, Punctuation, Whitespace]), "Paid");
+    // Normalization comes first, so curly quotes are folded before anything else.
+    assert_eq!(cleaned("“Don’t”", &[Punctuation, Normalize]), "Don't");
+}
+
+#[test]
+fn clean_without_steps_normalizes_lowercases_and_strips_punctuation() {
+    assert_eq!(cleaned("  The “Well-Known”   café,\tagain ", &[]), "the well-known café again");
+}
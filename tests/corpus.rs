@@ -0,0 +1,181 @@
+// tests/corpus.rs
+//
+// Corpus measures checked against values worked out by hand on corpora small
+// enough to count on paper.
+
+use talea::runtime::corpus::{collocation_table, frequency_table, ngrams_of};
+use talea::runtime::stopwords;
+use talea::runtime::table::Table;
+use talea::runtime::TaleaValue;
+
+fn documents(texts: &[&str]) -> Vec<(String, Vec<String>)> {
+    texts.iter().enumerate()
+        .map(|(i, text)| (format!("doc{}", i + 1), text.split_whitespace().map(str::to_string).collect()))
+        .collect()
+}
+
+fn column(table: &Table, name: &str) -> usize {
+    table.columns.iter().position(|column| column.name == name).unwrap_or_else(|| panic!("no column '{}'", name))
+}
+
+fn cell(table: &Table, collocate: &str, name: &str) -> f64 {
+    cell_of(table, "collocate", collocate, name)
+}
+
+// The number in column `name` of the row whose `key` column holds `item`.
+fn cell_of(table: &Table, key: &str, item: &str, name: &str) -> f64 {
+    let key = column(table, key);
+    let row = table.rows.iter()
+        .find(|row| matches!(&row[key], TaleaValue::String(text) if text == item))
+        .unwrap_or_else(|| panic!("no row for '{}'", item));
+    match row[column(table, name)] {
+        TaleaValue::Number(n) => n as f64,
+        TaleaValue::Float(f) => f,
+        ref other => panic!("'{}' is not a number: {:?}", name, other),
+    }
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 1e-9, "expected {}, got {}", expected, actual);
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split_whitespace().map(str::to_string).collect()
+}
+
+fn texts(table: &Table, name: &str) -> Vec<String> {
+    let index = column(table, name);
+    table.rows.iter().map(|row| row[index].display(None)).collect()
+}
+
+fn collocates(table: &Table) -> Vec<String> {
+    let word = column(table, "collocate");
+    let mut words: Vec<String> = table.rows.iter().map(|row| row[word].display(None)).collect();
+    words.sort();
+    words
+}
+
+#[test]
+fn frequency_lists_rank_by_count_and_share_ranks_between_ties() {
+    let table = frequency_table(&words("the cat sat the cat sat the mat"), "word", false);
+    let names: Vec<&str> = table.columns.iter().map(|column| column.name.as_str()).collect();
+    assert_eq!(names, ["rank", "word", "count", "per_million"]);
+    // Tied words are listed alphabetically.
+    assert_eq!(texts(&table, "word"), ["the", "cat", "sat", "mat"]);
+    assert_eq!(texts(&table, "count"), ["3", "2", "2", "1"]);
+    assert_eq!(texts(&table, "rank"), ["1", "2", "2", "4"]);
+}
+
+#[test]
+fn per_million_is_relative_to_the_number_of_items() {
+    let table = frequency_table(&words("the cat sat the cat sat the mat"), "word", false);
+    assert_close(cell_of(&table, "word", "the", "per_million"), 3.0 / 8.0 * 1_000_000.0);
+    assert_close(cell_of(&table, "word", "mat", "per_million"), 125_000.0);
+    let total: f64 = ["the", "cat", "sat", "mat"].iter().map(|word| cell_of(&table, "word", word, "per_million")).sum();
+    assert_close(total, 1_000_000.0);
+}
+
+#[test]
+fn frequency_lists_can_ignore_case() {
+    let table = frequency_table(&words("The the THE cat"), "word", true);
+    assert_eq!(texts(&table, "word"), ["the", "cat"]);
+    assert_eq!(texts(&table, "count"), ["3", "1"]);
+
+    let table = frequency_table(&words("The the THE cat"), "word", false);
+    assert_eq!(texts(&table, "count"), ["1", "1", "1", "1"]);
+    assert_eq!(texts(&table, "rank"), ["1", "1", "1", "1"]);
+}
+
+// "a x b x a c" and "c d x", node "x", window 1. The windows cover
+// positions {0, 2, 4} of the first document ("a", "b", "a": the "b" between
+// the two hits counts once) and position {1} of the second ("d"; the window
+// stops at the end of the text). So the window holds 4 words, the corpus 9,
+// and x occurs 3 times.
+#[test]
+fn collocation_measures_match_hand_computed_values() {
+    let table = collocation_table(&documents(&["a x b x a c", "c d x"]), "x", 1, "mi").unwrap();
+    assert_eq!(collocates(&table), ["a", "b", "d"]);
+
+    // "a": observed 2, corpus frequency 2, expected 4 × 2 / 9.
+    let expected = 8.0 / 9.0;
+    assert_close(cell(&table, "a", "observed"), 2.0);
+    assert_close(cell(&table, "a", "frequency"), 2.0);
+    assert_close(cell(&table, "a", "expected"), expected);
+    assert_close(cell(&table, "a", "mi"), (2.0 / expected).log2());
+    assert_close(cell(&table, "a", "t_score"), (2.0 - expected) / 2.0f64.sqrt());
+    // Window/outside × a/other: observed 2, 2, 0, 5 against expected 8/9, 28/9, 10/9, 35/9.
+    let g2 = 2.0 * (2.0 * (9.0f64 / 4.0).ln() + 2.0 * (9.0f64 / 14.0).ln() + 5.0 * (9.0f64 / 7.0).ln());
+    assert_close(cell(&table, "a", "log_likelihood"), g2);
+    assert_close(cell(&table, "a", "log_dice"), 14.0 + (4.0f64 / 5.0).log2());
+
+    // "b": observed 1 of its 1 occurrence, expected 4 × 1 / 9.
+    let expected = 4.0 / 9.0;
+    assert_close(cell(&table, "b", "expected"), expected);
+    assert_close(cell(&table, "b", "mi"), (1.0 / expected).log2());
+    assert_close(cell(&table, "b", "t_score"), 1.0 - expected);
+    // Observed 1, 3, 0, 5 against expected 4/9, 32/9, 5/9, 40/9.
+    let g2 = 2.0 * ((9.0f64 / 4.0).ln() + 3.0 * (27.0f64 / 32.0).ln() + 5.0 * (9.0f64 / 8.0).ln());
+    assert_close(cell(&table, "b", "log_likelihood"), g2);
+    assert_close(cell(&table, "b", "log_dice"), 14.0 + (2.0 / 4.0f64).log2());
+}
+
+#[test]
+fn overlapping_windows_count_each_word_once() {
+    // Both hits of "is" have "this" in their window, but it occurs only once.
+    let table = collocation_table(&documents(&["is this is"]), "is", 1, "log_dice").unwrap();
+    assert_eq!(collocates(&table), ["this"]);
+    assert_close(cell(&table, "this", "observed"), 1.0);
+    assert_close(cell(&table, "this", "expected"), 1.0 / 3.0);
+}
+
+#[test]
+fn observed_never_exceeds_frequency_and_log_dice_stays_at_most_14() {
+    let table = collocation_table(&documents(&["a a x a a x a"]), "x", 3, "mi").unwrap();
+    assert_eq!(collocates(&table), ["a"]);
+    assert_close(cell(&table, "a", "observed"), 5.0);
+    assert_close(cell(&table, "a", "frequency"), 5.0);
+    // Co-occurrences are capped at the node's 2 occurrences: 14 + log2(4 / 7).
+    assert_close(cell(&table, "a", "log_dice"), 14.0 + (4.0f64 / 7.0).log2());
+    assert!(cell(&table, "a", "log_dice") <= 14.0);
+}
+
+#[test]
+fn the_node_is_not_its_own_collocate() {
+    let table = collocation_table(&documents(&["x x y"]), "x", 2, "mi").unwrap();
+    assert_eq!(collocates(&table), ["y"]);
+}
+
+#[test]
+fn ngrams_are_runs_of_consecutive_words() {
+    assert_eq!(ngrams_of(&words("a b c d"), 2, None), ["a b", "b c", "c d"]);
+    assert_eq!(ngrams_of(&words("a b c d"), 3, None), ["a b c", "b c d"]);
+    assert!(ngrams_of(&words("a b"), 3, None).is_empty());
+}
+
+#[test]
+fn ngrams_stop_at_the_end_of_a_sentence() {
+    let text = words("The cat sat. It ran! Did it? \"Yes.\" Then home");
+    assert_eq!(ngrams_of(&text, 2, None), ["The cat", "cat sat", "It ran", "Did it", "Then home"]);
+    assert_eq!(ngrams_of(&text, 3, None), ["The cat sat"]);
+}
+
+#[test]
+fn ngrams_drop_attached_punctuation() {
+    assert_eq!(ngrams_of(&words("(well, \"quite\" so"), 2, None), ["well quite", "quite so"]);
+    // A dash on its own is not a word, so the words either side become neighbours.
+    assert_eq!(ngrams_of(&words("yes - no"), 2, None), ["yes no"]);
+    // Punctuation inside a word stays.
+    assert_eq!(ngrams_of(&words("don't stop"), 2, None), ["don't stop"]);
+}
+
+#[test]
+fn skipping_stopwords_only_looks_at_the_edges_of_an_ngram() {
+    let english = stopwords::set_of(stopwords::ENGLISH);
+    let text = words("freedom of speech is the right of all");
+    // "freedom of speech" keeps its inner "of"; anything starting or ending
+    // with a stopword goes.
+    assert_eq!(ngrams_of(&text, 3, Some(&english)), ["freedom of speech"]);
+    assert_eq!(ngrams_of(&text, 2, Some(&english)), Vec::<String>::new());
+    // Stopwords are found whatever their case or punctuation.
+    assert_eq!(ngrams_of(&words("The right, Freedom"), 2, Some(&english)), ["right Freedom"]);
+}
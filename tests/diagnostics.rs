@@ -0,0 +1,97 @@
+// tests/diagnostics.rs
+//
+// How errors are shown: the caret under the offending text, hints, and the
+// "did you mean" guesses for misspelled words.
+
+use talea::diagnostics::{suggest, Diagnostic, Span};
+use talea::lexer::Lexer;
+use talea::parser::Parser;
+use talea::runtime::interpreter::Interpreter;
+
+// The report for the first runtime error in `source`.
+fn runtime_report(source: &str) -> String {
+    let output = Parser::new(Lexer::new(source).all_tokens()).parse();
+    assert!(output.is_ok(), "unexpected parse errors: {:?}", output.diagnostics);
+    let diagnostic = Interpreter::new().execute(output.statements).err().expect("expected a runtime error");
+    diagnostic.render("Runtime Error", source)
+}
+
+// The report for the first syntax error in `source`.
+fn parse_report(source: &str) -> String {
+    let output = Parser::new(Lexer::new(source).all_tokens()).parse();
+    output.diagnostics.first().expect("expected a parse error").render("Parse Error", source)
+}
+
+#[test]
+fn the_caret_sits_under_the_span() {
+    let source = "load \"a.txt\" x";
+    let diagnostic = Diagnostic::new("Expected `as` here, but found the name `x`.", Span::new(13, 14, 1, 14))
+        .with_hint("did you mean `as`?");
+    assert_eq!(
+        diagnostic.render("Parse Error", source),
+        "Parse Error: Expected `as` here, but found the name `x`.\n --> line 1, column 14\n  |\n1 | load \"a.txt\" x\n  |              ^\n  = hint: did you mean `as`?\n"
+    );
+}
+
+#[test]
+fn the_caret_covers_the_whole_span_on_the_right_line() {
+    let source = "define a as 1\ndefine b as 2\nprint missing";
+    let report = Diagnostic::new("Variable 'missing' not found.", Span::new(34, 41, 3, 7)).render("Runtime Error", source);
+    assert!(report.contains("3 | print missing\n  |       ^^^^^^^\n"), "{}", report);
+    // The gutter widens with the line number.
+    let source = format!("{}print x", "\n".repeat(11));
+    let report = Diagnostic::new("Oops.", Span::new(17, 18, 12, 7)).render("Runtime Error", &source);
+    assert!(report.contains("   |\n12 | print x\n   |       ^\n"), "{}", report);
+}
+
+#[test]
+fn columns_after_multibyte_characters_count_characters() {
+    let source = "define ἀρετή as \"virtue\"\nprint ἀρετή + 1";
+    let report = runtime_report(source);
+    assert!(report.contains("--> line 2, column 7"), "{}", report);
+    assert!(report.contains("2 | print ἀρετή + 1\n  |       ^^^^^\n"), "{}", report);
+}
+
+#[test]
+fn a_caret_at_the_end_of_the_script_stays_on_the_line() {
+    let report = parse_report("load \"a.txt\" as");
+    assert!(report.contains("1 | load \"a.txt\" as\n  |                ^\n"), "{}", report);
+}
+
+#[test]
+fn runtime_errors_underline_the_operand_at_fault() {
+    let report = runtime_report("print \"abc\" + 1");
+    assert!(report.contains("1 | print \"abc\" + 1\n  |       ^^^^^\n"), "{}", report);
+    let report = runtime_report("print 1 + \"abc\"");
+    assert!(report.contains("  |           ^^^^^\n"), "{}", report);
+    let report = runtime_report("define x as 10 / (5 - 5)");
+    assert!(report.contains("  |                  ^^^^^^^\n"), "{}", report);
+    // Errors that no single operand caused still cover the whole statement.
+    let report = runtime_report("print missing");
+    assert!(report.contains("  | ^^^^^^^^^^^^^\n"), "{}", report);
+}
+
+#[test]
+fn hints_and_guesses_for_misspelled_words() {
+    let report = parse_report("lod \"a.txt\" as text");
+    assert!(report.contains("= hint: did you mean `load`?"), "{}", report);
+    let report = parse_report("define x ase 1");
+    assert!(report.contains("= hint: did you mean `as`?"), "{}", report);
+    let report = runtime_report("define words as 1\nprint wrods");
+    assert!(report.contains("Did you mean 'words'?"), "{}", report);
+}
+
+#[test]
+fn suggest_picks_the_closest_plausible_word() {
+    let commands = ["load", "save", "print", "tokenize", "count"];
+    assert_eq!(suggest("lod", commands), Some("load"));
+    assert_eq!(suggest("PRNIT", commands), Some("print"));
+    assert_eq!(suggest("tokenise", commands), Some("tokenize"));
+    // Too far from anything, an exact match, or too short to guess.
+    assert_eq!(suggest("lexicon", commands), None);
+    assert_eq!(suggest("load", commands), None);
+    assert_eq!(suggest("sv", commands), None);
+    // Three-letter words may only be one edit away.
+    assert_eq!(suggest("cnt", ["count", "cat"]), Some("cat"));
+    assert_eq!(suggest("xyz", ["xab"]), None);
+}
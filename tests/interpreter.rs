@@ -0,0 +1,429 @@
+// tests/interpreter.rs
+//
+// Whole scripts run through the lexer, parser and interpreter, checking the
+// values they leave behind.
+
+use talea::diagnostics::Diagnostic;
+use talea::lexer::Lexer;
+use talea::parser::Parser;
+use talea::runtime::interpreter::{ControlFlow, Interpreter};
+use talea::runtime::TaleaValue;
+
+fn run(source: &str) -> Result<Interpreter, Diagnostic> {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.all_tokens();
+    assert!(lexer.errors().is_empty(), "unexpected lexer errors: {:?}", lexer.errors());
+    let output = Parser::new(tokens).parse();
+    assert!(output.is_ok(), "unexpected parse errors: {:?}", output.diagnostics);
+    let mut interpreter = Interpreter::new();
+    interpreter.execute(output.statements)?;
+    Ok(interpreter)
+}
+
+fn value_of(source: &str, name: &str) -> TaleaValue {
+    let interpreter = run(source).unwrap_or_else(|e| panic!("runtime error: {}", e.message));
+    interpreter.variable(name).unwrap_or_else(|| panic!("'{}' is not defined", name))
+}
+
+fn error_of(source: &str) -> String {
+    match run(source) {
+        Ok(_) => panic!("expected a runtime error"),
+        Err(diagnostic) => diagnostic.message,
+    }
+}
+
+fn number(source: &str) -> i64 {
+    number_of(source, "x")
+}
+
+fn number_of(source: &str, name: &str) -> i64 {
+    match value_of(source, name) {
+        TaleaValue::Number(n) => n,
+        other => panic!("expected a whole number, got {:?}", other),
+    }
+}
+
+fn float(source: &str) -> f64 {
+    match value_of(source, "x") {
+        TaleaValue::Float(f) => f,
+        other => panic!("expected a decimal number, got {:?}", other),
+    }
+}
+
+fn boolean(source: &str) -> bool {
+    match value_of(source, "x") {
+        TaleaValue::Boolean(b) => b,
+        other => panic!("expected true or false, got {:?}", other),
+    }
+}
+
+// The cells of one column of the table in `x`, as they are printed.
+fn column_of(source: &str, name: &str) -> Vec<String> {
+    let TaleaValue::Table(table) = value_of(source, "x") else { panic!("expected a table") };
+    let index = table.columns.iter().position(|column| column.name == name).unwrap_or_else(|| panic!("no column '{}'", name));
+    table.rows.iter().map(|row| row[index].display(None)).collect()
+}
+
+#[test]
+fn exit_stops_the_script_and_is_left_to_the_caller() {
+    let output = Parser::new(Lexer::new("define x as 1\nexit\ndefine x as 2").all_tokens()).parse();
+    let mut interpreter = Interpreter::new();
+    assert_eq!(interpreter.execute(output.statements), Ok(ControlFlow::Exit));
+    assert!(matches!(interpreter.variable("x"), Some(TaleaValue::Number(1))));
+    // `exit` inside a loop or a procedure stops everything, not just the block.
+    let source = "define x as 0\ndefine procedure stop\n    exit\nend\nrepeat 3 times\n    set x to x + 1\n    stop\nend\nset x to 10";
+    assert_eq!(number(source), 1);
+    let output = Parser::new(Lexer::new("define x as 1").all_tokens()).parse();
+    assert_eq!(interpreter.execute(output.statements), Ok(ControlFlow::Continue));
+}
+
+#[test]
+fn multiplication_binds_tighter_than_addition() {
+    assert_eq!(number("define x as 2 + 3 * 4"), 14);
+    assert_eq!(number("define x as 2 * 3 + 4"), 10);
+    assert_eq!(number("define x as (2 + 3) * 4"), 20);
+    assert_eq!(number("define x as 20 - 6 / 2"), 17);
+}
+
+#[test]
+fn operators_of_equal_precedence_group_left_to_right() {
+    assert_eq!(number("define x as 10 - 4 - 3"), 3);
+    assert_eq!(number("define x as 24 / 4 / 2"), 3);
+}
+
+#[test]
+fn unary_minus_binds_tightest() {
+    assert_eq!(number("define x as -2 * 3"), -6);
+    assert_eq!(number("define x as 5 - -2"), 7);
+}
+
+#[test]
+fn divided_by_is_the_same_as_slash() {
+    assert_eq!(number("define x as 12 divided by 4"), 3);
+    assert_eq!(number("define x as 1 + 12 divided by 4 * 2"), 7);
+    assert_eq!(float("define x as 7 divided by 2"), 3.5);
+}
+
+#[test]
+fn whole_number_division_stays_whole_only_when_exact() {
+    assert_eq!(number("define x as 8 / 2"), 4);
+    assert_eq!(float("define x as 7 / 2"), 3.5);
+    assert_eq!(float("define x as -7 / 2"), -3.5);
+}
+
+#[test]
+fn any_float_makes_the_result_a_float() {
+    assert_eq!(float("define x as 1.5 + 1"), 2.5);
+    assert_eq!(float("define x as 8.0 / 2"), 4.0);
+    assert_eq!(float("define x as 2 * 0.25"), 0.5);
+}
+
+#[test]
+fn division_by_zero_is_an_error() {
+    assert_eq!(error_of("define x as 1 / 0"), "Division by zero.");
+    assert_eq!(error_of("define x as 1.5 divided by 0"), "Division by zero.");
+}
+
+#[test]
+fn whole_number_overflow_is_an_error() {
+    assert_eq!(error_of("define x as 9223372036854775807 + 1"), "The result is too large to represent.");
+    assert_eq!(error_of("define x as (-9223372036854775807 - 1) / -1"), "The result is too large to represent.");
+    assert_eq!(number("define x as (-9223372036854775807 - 1) / 1"), i64::MIN);
+}
+
+#[test]
+fn decimals_print_in_their_shortest_form() {
+    let shown = |source: &str| value_of(source, "x").display(None);
+    assert_eq!(shown("define x as 7 / 2"), "3.5");
+    assert_eq!(shown("define x as 0.1 + 0.2"), "0.3");
+    assert_eq!(shown("define x as 1 / 8"), "0.125");
+    // At most 6 decimals.
+    assert_eq!(shown("define x as 1 / 3"), "0.333333");
+    // Whole decimals keep a `.0` so they still read as decimals.
+    assert_eq!(shown("define x as 8.0 / 2"), "4.0");
+    assert_eq!(shown("define x as 0.5 * 0"), "0.0");
+}
+
+#[test]
+fn precision_fixes_the_number_of_decimals() {
+    let third = value_of("define x as 1 / 3", "x");
+    assert_eq!(third.display(Some(2)), "0.33");
+    assert_eq!(third.display(Some(0)), "0");
+    assert_eq!(third.display(Some(15)), "0.333333333333333");
+    assert_eq!(value_of("define x as 2.5", "x").display(Some(3)), "2.500");
+    // Whole numbers are never given decimals.
+    assert_eq!(value_of("define x as 2", "x").display(Some(3)), "2");
+}
+
+#[test]
+fn precision_must_be_between_0_and_15() {
+    assert!(run("set precision to 0\nset precision to 15").is_ok());
+    let message = "Precision must be a whole number of decimal places between 0 and 15.";
+    assert_eq!(error_of("set precision to 16"), message);
+    assert_eq!(error_of("set precision to -1"), message);
+    assert_eq!(error_of("set precision to 2.5"), message);
+}
+
+#[test]
+fn english_comparisons() {
+    assert!(boolean("define x as 5 is greater than 3"));
+    assert!(!boolean("define x as 5 is not greater than 3"));
+    assert!(boolean("define x as 3 is not greater than 3"));
+    assert!(boolean("define x as 3 is greater than or equal to 3"));
+    assert!(!boolean("define x as 2 is not less than 3"));
+    assert!(boolean("define x as 2 is less than or equal to 3"));
+    assert!(boolean("define x as 3 is 3"));
+    assert!(boolean("define x as 3 is not 4"));
+    assert!(boolean("define x as 3 is equal to 3.0"));
+}
+
+#[test]
+fn comparisons_bind_looser_than_arithmetic() {
+    assert!(boolean("define x as 2 + 2 is 4"));
+    assert!(boolean("define x as 10 / 4 > 2"));
+    assert!(!boolean("define x as 1 + 1 is greater than 1 * 2"));
+}
+
+#[test]
+fn and_binds_tighter_than_or_and_not_tighter_than_both() {
+    assert!(boolean("define x as true or false and false"));
+    assert!(!boolean("define x as (true or false) and false"));
+    assert!(boolean("define x as not false and true"));
+    assert!(!boolean("define x as not 1 + 1 is 2"));
+}
+
+#[test]
+fn errors_in_procedures_point_inside_them_and_list_the_calls() {
+    let source = "define procedure half taking n\n    return n / 0\nend\ndefine procedure outer taking n\n    half with n\nend\nouter with 4";
+    let diagnostic = run(source).err().expect("expected a runtime error");
+    assert_eq!(diagnostic.message, "Division by zero.");
+    assert_eq!(diagnostic.span.line, 2);
+    assert_eq!(diagnostic.trace, [("half".to_string(), 5), ("outer".to_string(), 7)]);
+}
+
+#[test]
+fn runaway_recursion_reports_the_depth_limit_once() {
+    let source = "define procedure forever taking n\n    forever with n\nend\nforever with 1";
+    let diagnostic = run(source).err().expect("expected a runtime error");
+    assert!(diagnostic.message.starts_with("Procedures called each other more than 200 levels deep."), "{}", diagnostic.message);
+    let report = diagnostic.render("Runtime Error", source);
+    assert_eq!(report.matches("in procedure 'forever'").count(), 5, "{}", report);
+    assert!(report.contains("... and 195 more calls"), "{}", report);
+}
+
+#[test]
+fn loop_variables_and_loop_definitions_end_with_the_loop() {
+    let interpreter = run("for each word in [\"a\", \"b\"]\n    define size as 1\nend").unwrap();
+    assert!(interpreter.variable("word").is_none());
+    assert!(interpreter.variable("size").is_none());
+    assert!(error_of("for each n in [1, 2]\nend\nprint n").contains("'n'"));
+}
+
+#[test]
+fn define_in_a_loop_shadows_while_set_updates_the_outer_variable() {
+    let source = "define total as 0\ndefine last as 0\nfor each n in [1, 2, 3]\n    define last as n\n    set total to total + n\nend";
+    assert_eq!(number_of(source, "total"), 6);
+    assert_eq!(number_of(source, "last"), 0);
+}
+
+#[test]
+fn assign_and_command_destinations_update_the_outer_variable() {
+    assert_eq!(number_of("define total as 1\nrepeat 3 times\n    assign total * 2 to total\nend", "total"), 8);
+    assert_eq!(number_of("define total as 0\nfor each n in [1, 2, 3]\n    add n to total as total\nend", "total"), 6);
+}
+
+#[test]
+fn set_needs_an_existing_variable() {
+    assert_eq!(error_of("set y to 1"), "Cannot set 'y' because it has not been defined yet. Use `define y as ...` first.");
+    // A variable defined inside a loop is gone by the time the loop is over.
+    assert!(error_of("repeat 1 times\n    define y as 1\nend\nset y to 2").starts_with("Cannot set 'y'"));
+}
+
+#[test]
+fn procedure_variables_stay_inside_the_procedure() {
+    let source = "define procedure f taking n\n    define doubled as n * 2\n    return doubled\nend\nf with 4 as x";
+    assert_eq!(number(source), 8);
+    let interpreter = run(source).unwrap();
+    assert!(interpreter.variable("doubled").is_none());
+    assert!(interpreter.variable("n").is_none());
+}
+
+#[test]
+fn a_destination_inside_a_procedure_never_overwrites_a_global() {
+    let source = "define total as 100\ndefine procedure f taking n\n    add 1 to n as total\n    return total\nend\nf with 4 as x";
+    assert_eq!(number(source), 5);
+    assert_eq!(number_of(source, "total"), 100);
+}
+
+#[test]
+fn set_inside_a_procedure_changes_the_variable_it_can_see() {
+    let source = "define count as 0\ndefine procedure bump\n    set count to count + 1\nend\nbump\nbump";
+    assert_eq!(number_of(source, "count"), 2);
+}
+
+#[test]
+fn procedures_see_where_they_were_defined_not_who_calls_them() {
+    let source = "define procedure secret_of_caller\n    return secret\nend\ndefine procedure caller\n    define secret as 1\n    secret_of_caller as x\nend\ncaller";
+    assert!(error_of(source).contains("'secret'"));
+}
+
+#[test]
+fn closures_capture_the_scope_they_were_defined_in() {
+    let source = "define procedure adder taking n\n    define procedure add_n taking m\n        return n + m\n    end\n    return add_n\nend\nadder with 10 as add_ten\nadd_ten with 5 as x";
+    assert_eq!(number(source), 15);
+    assert!(run(source).unwrap().variable("n").is_none());
+}
+
+#[test]
+fn closures_see_later_changes_to_captured_variables() {
+    let source = "define count as 1\ndefine procedure current\n    return count\nend\nset count to 2\ncurrent as x";
+    assert_eq!(number(source), 2);
+}
+
+#[test]
+fn frequency_ignoring_case_merges_capitalised_forms() {
+    let source = "frequency of words in \"The cat saw the dog and THE bird\" ignoring case as x";
+    assert_eq!(column_of(source, "word")[0], "the");
+    assert_eq!(column_of(source, "count")[0], "3");
+}
+
+#[test]
+fn top_and_bottom_pick_the_most_and_least_frequent_rows() {
+    let text = "\"a b b c c c d d d d\"";
+    let top = format!("frequency of words in {} as freq\ndefine x as top 2 of freq", text);
+    assert_eq!(column_of(&top, "word"), ["d", "c"]);
+    assert_eq!(column_of(&top, "rank"), ["1", "2"]);
+    let bottom = format!("frequency of words in {} as freq\ndefine x as bottom 2 of freq", text);
+    assert_eq!(column_of(&bottom, "word"), ["a", "b"]);
+    assert_eq!(column_of(&bottom, "count"), ["1", "2"]);
+    let all = format!("frequency of words in {} as freq\ndefine x as top 10 of freq", text);
+    assert_eq!(column_of(&all, "word").len(), 4);
+}
+
+#[test]
+fn commands_set_it_but_leave_other_variables_alone() {
+    let source = "define result as 42\ndefine words as [\"a\", \"b\"]\ncount words in words as n";
+    assert_eq!(number_of(source, "result"), 42);
+    assert_eq!(number_of(source, "it"), 2);
+}
+
+#[test]
+fn result_is_another_name_for_it() {
+    let source = "define words as [\"a\", \"b\"]\ncount words in words\ndefine x as result";
+    assert_eq!(number(source), 2);
+    assert_eq!(number_of(source, "result"), 2);
+    assert!(error_of("print result").starts_with("There is no previous result"));
+}
+
+#[test]
+fn a_pipeline_can_continue_on_the_next_lines() {
+    let source = "define text as \"the cat sat on the mat\"\ntokenize text\n    then count words as n\n    then add 1 to n as x";
+    assert_eq!(number_of(source, "n"), 6);
+    assert_eq!(number(source), 7);
+}
+
+#[test]
+fn a_procedure_stage_gets_the_previous_result_first() {
+    let source = "define procedure times_then_add taking value, factor, extra\n    return value * factor + extra\nend\n\
+        define words as [\"a\", \"b\", \"c\"]\ncount words in words then times_then_add with 10, 1 as x";
+    assert_eq!(number(source), 31);
+    // A procedure with no other arguments is called with just the result.
+    let source = "define procedure double taking value\n    return value * 2\nend\ndefine words as [\"a\"]\ncount words in words then double as x";
+    assert_eq!(number(source), 2);
+}
+
+// The items of the list in `x`, as they are printed.
+fn items_of(source: &str) -> Vec<String> {
+    match value_of(source, "x") {
+        TaleaValue::List(items) => items.iter().map(|item| item.display(None)).collect(),
+        other => panic!("expected a list, got {:?}", other),
+    }
+}
+
+#[test]
+fn sorting_text_follows_dictionary_order() {
+    // Code point order would put "Émile" after "ernie", and "Peng" before "Peña".
+    assert_eq!(items_of("sort [\"ernie\", \"Émile\", \"apple\"] as x"), ["apple", "Émile", "ernie"]);
+    assert_eq!(items_of("sort [\"Peng\", \"Peña\"] as x"), ["Peña", "Peng"]);
+    assert_eq!(items_of("sort [\"Peng\", \"Peña\"] descending as x"), ["Peng", "Peña"]);
+    // Numbers by value, not as text.
+    assert_eq!(items_of("sort [10, 9, 100, 2.5] as x"), ["2.5", "9", "10", "100"]);
+}
+
+#[test]
+fn later_sort_keys_break_ties_and_full_ties_keep_their_order() {
+    let people = "define x as table of [\n\
+        {name: \"ernie\", age: 30, id: 1},\n\
+        {name: \"Émile\", age: 40, id: 2},\n\
+        {name: \"apple\", age: 30, id: 3},\n\
+        {name: \"Émile\", age: 40, id: 4},\n\
+        {name: \"bob\", age: 40, id: 5},\n\
+    ]\n";
+    let source = format!("{}sort x by age descending, name as x", people);
+    assert_eq!(column_of(&source, "name"), ["bob", "Émile", "Émile", "apple", "ernie"]);
+    // Rows tied on every key stay in the order they came in, every time.
+    assert_eq!(column_of(&source, "id"), ["5", "2", "4", "3", "1"]);
+    let source = format!("{}sort x by age descending, name descending as x", people);
+    assert_eq!(column_of(&source, "id"), ["2", "4", "5", "1", "3"]);
+}
+
+#[test]
+fn fields_are_read_by_name() {
+    let book = "define book as {title: \"Odyssey\", year: 1614, author: {name: \"Homer\"}}\n";
+    assert_eq!(number(&format!("{}define x as the year of book", book)), 1614);
+    assert_eq!(value_of(&format!("{}define x as the name of the author of book", book), "x").display(None), "Homer");
+    // On a list of records, a field gives the value from each of them.
+    assert_eq!(items_of("define x as the n of [{n: 1}, {n: 2}]"), ["1", "2"]);
+}
+
+#[test]
+fn a_missing_field_is_an_error_that_lists_the_fields() {
+    let book = "define book as {title: \"Odyssey\", year: 1614}\n";
+    assert_eq!(error_of(&format!("{}print the yeer of book", book)), "The record has no field 'yeer'. Did you mean 'year'?");
+    assert_eq!(error_of(&format!("{}print the author of book", book)), "The record has no field 'author'. Its fields are: title, year.");
+}
+
+#[test]
+fn keys_and_values_keep_the_order_fields_were_written_in() {
+    let record = "define r as {zeta: 1, alpha: 2, mu: 3}\n";
+    assert_eq!(items_of(&format!("{}define x as keys of r", record)), ["zeta", "alpha", "mu"]);
+    assert_eq!(items_of(&format!("{}define x as values of r", record)), ["1", "2", "3"]);
+}
+
+#[test]
+fn records_print_one_field_per_line_with_the_values_lined_up() {
+    let source = "define x as {name: \"Reed\", year: 1962, tags: [\"poet\"], place: {city: \"Oslo\"}}";
+    assert_eq!(
+        value_of(source, "x").display(None),
+        "[Record with 4 fields]:\nname   Reed\nyear   1962\ntags   ['poet']\nplace  {city: 'Oslo'}"
+    );
+    // Inside a list, a record is shown inline.
+    assert_eq!(value_of("define x as [{a: 1, b: \"two\"}]", "x").display(None), "[List with 1 items]:\n[{a: 1, b: 'two'}]");
+}
+
+#[test]
+fn repeat_zero_times_runs_nothing() {
+    assert_eq!(number("define x as 5\nrepeat 0 times\n    set x to 0\nend"), 5);
+    assert_eq!(number("define x as 0\ndefine n as 2\nrepeat (n + 1) times\n    set x to x + 1\nend"), 3);
+    assert_eq!(error_of("repeat -1 times\nend"), "`repeat` needs a count of 0 or more, but got -1.");
+    assert!(error_of("repeat 1.5 times\nend").starts_with("`repeat` needs a whole number of times"));
+}
+
+#[test]
+fn for_each_goes_through_the_rows_of_a_table_as_records() {
+    let source = "define people as table of [{name: \"Reed\", age: 34}, {name: \"Okafor\", age: 51}]\n\
+        define x as 0\n\
+        for each person in people\n    set x to x + the age of person\nend";
+    assert_eq!(number(source), 85);
+    assert!(error_of("define people as table of [{name: \"Reed\"}]\nfor each person in people\n    print the age of person\nend").contains("no field 'age'"));
+}
+
+#[test]
+fn the_loop_variable_is_gone_after_the_loop_and_an_outer_one_is_kept() {
+    let source = "define word as \"outer\"\nfor each word in [\"a\", \"b\"]\n    define last as word\nend\ndefine x as word";
+    assert_eq!(value_of(source, "x").display(None), "outer");
+    assert!(error_of("repeat 2 times\n    define counter as 1\nend\nprint counter").contains("'counter'"));
+    // The same holds when `return` leaves the loop early.
+    let interpreter = run("define procedure f\n    for each n in [1, 2]\n        return n\n    end\nend\nf");
+    assert!(interpreter.unwrap().variable("n").is_none());
+}
@@ -0,0 +1,207 @@
+// tests/lexer.rs
+//
+// The lexer must handle any script our users write: accented names, Greek,
+// Devanagari and CJK text, and long pasted passages. Spans are byte offsets
+// into the source, so slicing the input with a token's span must give back
+// exactly the text of that token.
+
+use talea::diagnostics::Spanned;
+use talea::lexer::{Lexer, Token};
+
+fn lex(input: &str) -> Vec<Spanned<Token>> {
+    let mut lexer = Lexer::new(input);
+    let tokens = lexer.all_tokens();
+    assert!(lexer.errors().is_empty(), "unexpected lexer errors: {:?}", lexer.errors());
+    tokens
+}
+
+fn kinds(input: &str) -> Vec<Token> {
+    lex(input).into_iter().map(|t| t.node).collect()
+}
+
+fn ident(name: &str) -> Token {
+    Token::Identifier(name.to_string())
+}
+
+#[test]
+fn accented_identifiers() {
+    assert_eq!(
+        kinds("define café as \"crème brûlée\""),
+        vec![Token::Define, ident("café"), Token::As, Token::String("crème brûlée".to_string()), Token::Eof]
+    );
+}
+
+#[test]
+fn greek_identifiers_and_text() {
+    assert_eq!(
+        kinds("load \"Ὀδύσσεια.txt\" as ἔπος"),
+        vec![Token::Load, Token::String("Ὀδύσσεια.txt".to_string()), Token::As, ident("ἔπος"), Token::Eof]
+    );
+}
+
+#[test]
+fn devanagari_identifiers_keep_their_combining_marks() {
+    // "नमस्ते" contains a virama and vowel signs, which are not alphabetic on their own.
+    assert_eq!(
+        kinds("define नमस्ते as 1"),
+        vec![Token::Define, ident("नमस्ते"), Token::As, Token::Number(1), Token::Eof]
+    );
+}
+
+#[test]
+fn decomposed_accents_stay_in_one_identifier() {
+    // "é" written as "e" followed by U+0301 COMBINING ACUTE ACCENT.
+    let name = "re\u{301}sume\u{301}";
+    assert_eq!(kinds(&format!("print {}", name)), vec![Token::Print, ident(name), Token::Eof]);
+}
+
+#[test]
+fn cjk_identifiers() {
+    assert_eq!(kinds("print 源氏物語"), vec![Token::Print, ident("源氏物語"), Token::Eof]);
+}
+
+#[test]
+fn keywords_are_case_insensitive_for_non_ascii_neighbours() {
+    assert_eq!(
+        kinds("TOKENIZE Ἰλιάς AS λέξεις"),
+        vec![Token::Tokenize, ident("Ἰλιάς"), Token::As, ident("λέξεις"), Token::Eof]
+    );
+}
+
+#[test]
+fn spans_are_byte_ranges_into_the_source() {
+    let input = "define ñandú as \"Δελφοί\"\nprint ñandú";
+    for token in lex(input) {
+        let text = &input[token.span.start..token.span.end];
+        match &token.node {
+            Token::Identifier(name) => assert_eq!(text, name),
+            Token::String(s) => assert_eq!(text, format!("\"{}\"", s)),
+            Token::Define => assert_eq!(text, "define"),
+            Token::As => assert_eq!(text, "as"),
+            Token::Print => assert_eq!(text, "print"),
+            Token::Newline => assert_eq!(text, "\n"),
+            Token::Eof => assert_eq!(token.span.start, input.len()),
+            other => panic!("unexpected token {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn columns_count_characters_not_bytes() {
+    let tokens = lex("define ἀρετή as x");
+    let as_token = tokens.iter().find(|t| t.node == Token::As).unwrap();
+    assert_eq!(as_token.span.line, 1);
+    assert_eq!(as_token.span.column, 14);
+}
+
+#[test]
+fn lines_and_columns_after_multibyte_lines() {
+    let tokens = lex("define à as 1\nprint à");
+    let print = tokens.iter().find(|t| t.node == Token::Print).unwrap();
+    assert_eq!((print.span.line, print.span.column), (2, 1));
+    let last = tokens.iter().rev().find(|t| t.node == ident("à")).unwrap();
+    assert_eq!((last.span.line, last.span.column), (2, 7));
+}
+
+#[test]
+fn strings_keep_emoji_and_escapes() {
+    assert_eq!(
+        kinds("print \"🌿 \\u{1F33F} \\\"talea\\\"\""),
+        vec![Token::Print, Token::String("🌿 🌿 \"talea\"".to_string()), Token::Eof]
+    );
+}
+
+#[test]
+fn triple_quoted_multilingual_passage() {
+    let input = "define passage as \"\"\"Μῆνιν ἄειδε θεὰ\nयदा यदा हि धर्मस्य\"\"\"\nprint passage";
+    let tokens = lex(input);
+    assert_eq!(tokens[3].node, Token::String("Μῆνιν ἄειδε θεὰ\nयदा यदा हि धर्मस्य".to_string()));
+    // The statement after the passage is on line 3.
+    let print = tokens.iter().find(|t| t.node == Token::Print).unwrap();
+    assert_eq!(print.span.line, 3);
+}
+
+#[test]
+fn illegal_non_ascii_punctuation_is_reported_with_its_position() {
+    let input = "print «x»";
+    let mut lexer = Lexer::new(input);
+    let tokens = lexer.all_tokens();
+    assert_eq!(tokens[1].node, Token::Illegal("«".to_string()));
+    assert_eq!(&input[tokens[1].span.start..tokens[1].span.end], "«");
+    assert_eq!(lexer.errors().len(), 2);
+    assert_eq!(lexer.errors()[1].span.column, 9);
+}
+
+#[test]
+fn unterminated_string_with_non_ascii_text() {
+    let input = "define x as \"Straße\nprint x";
+    let mut lexer = Lexer::new(input);
+    let tokens = lexer.all_tokens();
+    assert_eq!(tokens[3].node, Token::Illegal("\"Straße".to_string()));
+    assert_eq!(lexer.errors().len(), 1);
+    assert_eq!(&input[lexer.errors()[0].span.start..lexer.errors()[0].span.end], "\"Straße");
+}
+
+#[test]
+fn long_input_is_scanned_in_one_pass() {
+    // Large enough that the old `chars().nth()` scanner would take minutes.
+    let passage = "Ἄνδρα μοι ἔννεπε, μοῦσα, πολύτροπον ".repeat(20_000);
+    let input = format!("define odyssey as \"{}\"", passage);
+    let tokens = lex(&input);
+    assert_eq!(tokens[3].node, Token::String(passage));
+}
+
+#[test]
+fn comments_may_contain_any_script() {
+    assert_eq!(
+        kinds("# Ἀριστοτέλης · 孔子 · कालिदास\nprint x"),
+        vec![Token::Newline, Token::Print, ident("x"), Token::Eof]
+    );
+}
+
+#[test]
+fn line_breaks_inside_brackets_do_not_end_the_statement() {
+    assert_eq!(
+        kinds("define ville as {\n  nom: \"Zürich\",\n}\nprint ville"),
+        vec![
+            Token::Define, ident("ville"), Token::As, Token::LeftBrace, ident("nom"), Token::Colon,
+            Token::String("Zürich".to_string()), Token::Comma, Token::RightBrace, Token::Newline,
+            Token::Print, ident("ville"), Token::Eof,
+        ]
+    );
+}
+
+#[test]
+fn numbers_too_large_to_hold_are_reported() {
+    assert_eq!(kinds("9223372036854775807"), vec![Token::Number(i64::MAX), Token::Eof]);
+    let input = "define x as 9223372036854775808";
+    let mut lexer = Lexer::new(input);
+    let tokens = lexer.all_tokens();
+    assert_eq!(tokens[3].node, Token::Illegal("9223372036854775808".to_string()));
+    assert_eq!(lexer.errors().len(), 1);
+    assert_eq!(lexer.errors()[0].message, "This number is too large.");
+    assert_eq!(lexer.errors()[0].span.column, 13);
+    // A decimal too large to hold would otherwise become infinity.
+    let input = format!("{}.5", "9".repeat(400));
+    let mut lexer = Lexer::new(&input);
+    lexer.all_tokens();
+    assert_eq!(lexer.errors().len(), 1);
+}
+
+#[test]
+fn input_that_stops_mid_statement_is_unfinished() {
+    let unfinished = |input: &str| {
+        let mut lexer = Lexer::new(input);
+        lexer.all_tokens();
+        lexer.is_unfinished()
+    };
+    assert!(unfinished("define words as [\"a\",\n"));
+    assert!(unfinished("define passage as \"\"\"Sing to me"));
+    assert!(unfinished("multiply a \\\n"));
+    assert!(unfinished("multiply a \\   # by ten\n"));
+    assert!(!unfinished("define words as [\"a\"]\n"));
+    assert!(!unfinished("define passage as \"\"\"Sing\nto me\"\"\""));
+    assert!(!unfinished("multiply a \\\n    by 10 as c"));
+    // An ordinary string can't run over a line, so waiting would not help.
+    assert!(!unfinished("define title as \"Odyssey"));
+}
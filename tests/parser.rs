@@ -0,0 +1,100 @@
+// tests/parser.rs
+//
+// Parsing whole scripts: which statements come out, and which errors are
+// reported when some of them are wrong.
+
+use talea::ast::Statement;
+use talea::lexer::Lexer;
+use talea::parser::{open_blocks, ParseOutput, Parser};
+use talea::runtime::interpreter::Interpreter;
+use talea::runtime::TaleaValue;
+
+fn parse(source: &str) -> ParseOutput {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.all_tokens();
+    assert!(lexer.errors().is_empty(), "unexpected lexer errors: {:?}", lexer.errors());
+    Parser::new(tokens).parse()
+}
+
+fn error_lines(output: &ParseOutput) -> Vec<usize> {
+    output.diagnostics.iter().map(|diagnostic| diagnostic.span.line).collect()
+}
+
+fn blocks(source: &str) -> usize {
+    open_blocks(&Lexer::new(source).all_tokens())
+}
+
+#[test]
+fn every_error_in_a_script_is_reported() {
+    let output = parse("load as x\ndefine as 1\nload \"article.txt\" as\nprint 1");
+    assert_eq!(error_lines(&output), [1, 2, 3]);
+    assert_eq!(output.statements.len(), 1);
+}
+
+#[test]
+fn recovery_resumes_at_the_next_line() {
+    let output = parse("define x 1 2 3\nprint x");
+    assert_eq!(error_lines(&output), [1]);
+    assert!(matches!(output.statements[..], [ref print] if matches!(print.node, Statement::Print(_))));
+}
+
+#[test]
+fn recovery_resumes_after_a_terminator() {
+    let output = parse("define x 1 2; print x. define y 3");
+    assert_eq!(output.diagnostics.len(), 2);
+    assert_eq!(output.diagnostics[1].span.column, 33);
+    assert_eq!(output.statements.len(), 1);
+}
+
+#[test]
+fn recovery_resumes_at_a_command_keyword() {
+    // No separator after the mistake: the parser picks up again at `print`.
+    let output = parse("define x 1 print x");
+    assert_eq!(output.diagnostics.len(), 1);
+    assert_eq!(output.diagnostics[0].span.column, 10);
+    assert!(matches!(output.statements[..], [ref print] if matches!(print.node, Statement::Print(_))));
+}
+
+#[test]
+fn recovery_inside_a_block_keeps_the_block() {
+    let output = parse("if true\n    define y 2\n    print 1\nend\nprint 2");
+    assert_eq!(error_lines(&output), [2]);
+    assert_eq!(output.statements.len(), 2);
+    let Statement::If { then_branch, .. } = &output.statements[0].node else { panic!("expected an if block") };
+    assert_eq!(then_branch.len(), 1);
+}
+
+#[test]
+fn statements_after_an_error_still_parse_and_run() {
+    let output = parse("define x as 1\ndefine y 2\nadd 1 to x as x\nmultiply x by 10 as z");
+    assert_eq!(error_lines(&output), [2]);
+    assert_eq!(output.statements.len(), 3);
+    let mut interpreter = Interpreter::new();
+    interpreter.execute(output.statements).unwrap();
+    assert!(matches!(interpreter.variable("x"), Some(TaleaValue::Number(2))));
+    assert!(matches!(interpreter.variable("z"), Some(TaleaValue::Number(20))));
+}
+
+#[test]
+fn an_empty_pipeline_stage_is_an_error() {
+    for source in ["tokenize text then", "tokenize text then\nprint it", "tokenize text then; print it"] {
+        let output = parse(source);
+        assert_eq!(output.diagnostics.len(), 1, "{}", source);
+        assert_eq!(output.diagnostics[0].message, "Expected a command after `then`.");
+        assert_eq!(output.diagnostics[0].span.column, 19, "{}", source);
+    }
+}
+
+#[test]
+fn open_blocks_counts_blocks_still_waiting_for_end() {
+    assert_eq!(blocks("print 1"), 0);
+    assert_eq!(blocks("if x > 1"), 1);
+    assert_eq!(blocks("if x > 1\n    print x\notherwise if x is 1\n    print 1\notherwise"), 1);
+    assert_eq!(blocks("if x > 1\n    print x\nend"), 0);
+    assert_eq!(blocks("for each word in words\n    repeat 2 times\n        print word"), 2);
+    assert_eq!(blocks("for each word in words\n    repeat 2 times\n        print word\n    end"), 1);
+    assert_eq!(blocks("define procedure greet taking name\n    print name"), 1);
+    assert_eq!(blocks("define procedure greet taking name\n    print name\nend"), 0);
+    // A stray `end` is left for the parser to report.
+    assert_eq!(blocks("end\nif true"), 1);
+}
@@ -0,0 +1,87 @@
+// tests/patterns.rs
+//
+// `replace`: literal text against regular expressions, group references in
+// the replacement, and replacing through a list.
+
+use talea::lexer::Lexer;
+use talea::parser::Parser;
+use talea::runtime::interpreter::Interpreter;
+use talea::runtime::patterns::{compile, replace_in};
+use talea::runtime::TaleaValue;
+
+// The value of `x` after running `source`, as it is printed.
+fn x_after(source: &str) -> String {
+    let output = Parser::new(Lexer::new(source).all_tokens()).parse();
+    assert!(output.is_ok(), "unexpected parse errors: {:?}", output.diagnostics);
+    let mut interpreter = Interpreter::new();
+    interpreter.execute(output.statements).unwrap_or_else(|e| panic!("runtime error: {}", e.message));
+    interpreter.variable("x").expect("'x' is not defined").display(None)
+}
+
+fn replaced(text: &str, pattern: &str, replacement: &str) -> String {
+    match replace_in(&TaleaValue::String(text.to_string()), &compile(pattern, false).unwrap(), replacement, false).unwrap() {
+        (TaleaValue::String(text), _) => text,
+        other => panic!("expected text, got {:?}", other),
+    }
+}
+
+fn strings(items: &[&str]) -> TaleaValue {
+    TaleaValue::List(items.iter().map(|item| TaleaValue::String(item.to_string())).collect())
+}
+
+fn items(list: TaleaValue) -> Vec<String> {
+    match list {
+        TaleaValue::List(items) => items.iter().map(|item| item.display(None)).collect(),
+        other => panic!("expected a list, got {:?}", other),
+    }
+}
+
+#[test]
+fn plain_replace_takes_the_pattern_and_replacement_literally() {
+    let source = "define text as \"costs $5 (or 5.0)\"\nreplace \"(or 5.0)\" with \"$1\" in text as x";
+    assert_eq!(x_after(source), "costs $5 $1");
+    // `.` is an ordinary character, not "any character".
+    assert_eq!(x_after("replace \".\" with \"!\" in \"a.b\" as x"), "a!b");
+}
+
+#[test]
+fn replace_regex_reads_a_regular_expression() {
+    assert_eq!(x_after("replace regex \".\" with \"!\" in \"a.b\" as x"), "!!!");
+    assert_eq!(x_after("replace regex \"\\\\s+\" with \" \" in \"a  b\\t\\nc\" as x"), "a b c");
+    assert_eq!(x_after("replace regex \"the\" with \"a\" in \"The theme\" ignoring case as x"), "a ame");
+}
+
+#[test]
+fn numbered_groups_end_at_their_last_digit() {
+    // The engine would read `$1ed` as a group called "1ed".
+    assert_eq!(replaced("walk talk", r"(\w+)", "$1ed"), "walked talked");
+    assert_eq!(replaced("ab", "(a)(b)", "$2$1"), "ba");
+    assert_eq!(replaced("abcdefghijk", "(a)(b)(c)(d)(e)(f)(g)(h)(i)(j)(k)", "$11-$1"), "k-a");
+}
+
+#[test]
+fn a_double_dollar_is_a_dollar_sign() {
+    assert_eq!(replaced("5 dollars", r"(\d+) dollars", "$$$1"), "$5");
+    assert_eq!(replaced("price", "price", "$$1"), "$1");
+}
+
+#[test]
+fn named_groups_are_written_in_braces() {
+    assert_eq!(replaced("loveth hateth", r"(?P<stem>\w+)eth\b", "${stem}es"), "loves hates");
+}
+
+#[test]
+fn lists_are_replaced_item_by_item_with_a_total_count() {
+    let words = TaleaValue::List(vec![
+        TaleaValue::String("ſo".to_string()),
+        TaleaValue::Number(3),
+        TaleaValue::String("ſhe ſaid".to_string()),
+        TaleaValue::String("yes".to_string()),
+    ]);
+    let (result, count) = replace_in(&words, &compile("ſ", false).unwrap(), "s", true).unwrap();
+    assert_eq!(count, 3);
+    assert_eq!(items(result), ["so", "3", "she said", "yes"]);
+    let (result, count) = replace_in(&strings(&["cat", "dog"]), &compile("z", false).unwrap(), "y", true).unwrap();
+    assert_eq!(items(result), ["cat", "dog"]);
+    assert_eq!(count, 0);
+}
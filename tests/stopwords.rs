@@ -0,0 +1,92 @@
+// tests/stopwords.rs
+//
+// The built-in stopword lists, stopword files, and `load stopwords` making a
+// file the list every later command uses.
+
+use std::fs;
+
+use talea::lexer::Lexer;
+use talea::parser::Parser;
+use talea::runtime::interpreter::Interpreter;
+use talea::runtime::stopwords::{builtin, is_stopword, parse_list, LANGUAGES};
+use talea::runtime::TaleaValue;
+
+// The text in `x` after running `source`.
+fn text_after(source: &str) -> String {
+    let output = Parser::new(Lexer::new(source).all_tokens()).parse();
+    assert!(output.is_ok(), "unexpected parse errors: {:?}", output.diagnostics);
+    let mut interpreter = Interpreter::new();
+    interpreter.execute(output.statements).unwrap_or_else(|e| panic!("runtime error: {}", e.message));
+    match interpreter.variable("x") {
+        Some(TaleaValue::String(text)) => text,
+        other => panic!("expected text, got {:?}", other),
+    }
+}
+
+#[test]
+fn every_language_has_a_list_with_its_commonest_words() {
+    let samples = [
+        ("english", ["the", "and", "of"]),
+        ("german", ["der", "und", "über"]),
+        ("french", ["le", "et", "où"]),
+        ("spanish", ["el", "y", "también"]),
+        ("italian", ["il", "e", "perché"]),
+        ("latin", ["et", "sive", "siue"]),
+        ("greek", ["καί", "τοῦ", "ἀλλά"]),
+    ];
+    assert_eq!(samples.len(), LANGUAGES.len());
+    for (language, words) in samples {
+        let list = builtin(language).unwrap_or_else(|| panic!("no list for {}", language));
+        for word in words {
+            assert!(is_stopword(&list, word), "'{}' is not an {} stopword", word, language);
+        }
+        // Content words are not stopwords in any language.
+        assert!(!is_stopword(&list, "odyssey"), "{}", language);
+    }
+    assert!(builtin("ENGLISH").is_some());
+    assert!(builtin("ancient_greek").is_some());
+    assert!(builtin("klingon").is_none());
+}
+
+#[test]
+fn stopwords_match_whatever_the_case_punctuation_or_normal_form() {
+    let french = builtin("french").unwrap();
+    assert!(is_stopword(&french, "Où,"));
+    // "été" typed with combining accents.
+    assert!(is_stopword(&french, "e\u{301}te\u{301}"));
+}
+
+#[test]
+fn greek_words_also_match_with_a_grave_accent() {
+    let greek = builtin("greek").unwrap();
+    // Before another word, a final acute accent is written grave.
+    for word in ["καὶ", "τὸ", "ἀλλὰ", "αὐτὸς", "ἐστὶ"] {
+        assert!(is_stopword(&greek, word), "{}", word);
+    }
+    assert!(is_stopword(&greek, "και\u{300}"));
+    // Only an accent on the last vowel changes: "ἄρα" has no "ἂρα", and
+    // circumflexes stay as they are.
+    assert!(!is_stopword(&greek, "ἂρα"));
+    assert!(is_stopword(&greek, "τοῦ"));
+}
+
+#[test]
+fn stopword_files_allow_commas_spaces_and_comments() {
+    let text = "# My list\nthe, a  an\n   # indented comment\nof,,to\n\nCorpus";
+    assert_eq!(parse_list(text), ["the", "a", "an", "of", "to", "Corpus"]);
+    assert!(parse_list("# only comments\n\n").is_empty());
+}
+
+#[test]
+fn load_stopwords_replaces_the_active_list() {
+    let path = std::env::temp_dir().join(format!("talea_stopwords_{}.txt", std::process::id()));
+    fs::write(&path, "# names to ignore\nodysseus, penelope\n").unwrap();
+    let text = "define text as \"Odysseus and the suitors of Penelope\"\n";
+    // English by default.
+    assert_eq!(text_after(&format!("{}remove stopwords from text as x", text)), "Odysseus suitors Penelope");
+    // After loading, only the file's words are stopwords.
+    let source = format!("{}load stopwords from \"{}\" as mine\nremove stopwords from text as x", text, path.display());
+    let result = text_after(&source);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(result, "and the suitors of");
+}
@@ -0,0 +1,135 @@
+// tests/table.rs
+//
+// Building tables, directly, from `find` results and by ranking; printing,
+// saving, filtering and selecting them.
+
+use talea::lexer::Lexer;
+use talea::parser::Parser;
+use talea::runtime::corpus::concordance_table;
+use talea::runtime::interpreter::Interpreter;
+use talea::runtime::patterns::{compile, matches_table};
+use talea::runtime::table::Table;
+use talea::runtime::TaleaValue;
+
+fn names(table: &Table) -> Vec<&str> {
+    table.column_names()
+}
+
+fn text(value: &str) -> TaleaValue {
+    TaleaValue::String(value.to_string())
+}
+
+fn people() -> Table {
+    Table::new(vec!["name".to_string(), "note".to_string()], vec![
+        vec![text("Reed, Evelyn"), text("said \"hi\"")],
+        vec![text("Tab\tby"), text("two\nlines")],
+        vec![text("Plain"), TaleaValue::Number(3)],
+    ]).unwrap()
+}
+
+// The table in `x` after running `source`.
+fn table_after(source: &str) -> Table {
+    let output = Parser::new(Lexer::new(source).all_tokens()).parse();
+    assert!(output.is_ok(), "unexpected parse errors: {:?}", output.diagnostics);
+    let mut interpreter = Interpreter::new();
+    interpreter.execute(output.statements).unwrap_or_else(|e| panic!("runtime error: {}", e.message));
+    match interpreter.variable("x") {
+        Some(TaleaValue::Table(table)) => table,
+        other => panic!("expected a table, got {:?}", other),
+    }
+}
+
+#[test]
+fn column_names_must_be_unique() {
+    let error = Table::new(vec!["word".to_string(), "count".to_string(), "word".to_string()], Vec::new()).unwrap_err();
+    assert_eq!(error, "A table cannot have two columns called 'word'.");
+    let table = Table::new(vec!["word".to_string(), "count".to_string()], Vec::new()).unwrap();
+    assert!(table.select(&["word".to_string(), "word".to_string()]).is_err());
+}
+
+#[test]
+fn find_results_have_a_column_per_group() {
+    let regex = compile(r"(?P<stem>\w+)(ed|ing)\b", false).unwrap();
+    let table = matches_table(&TaleaValue::String("walked singing".to_string()), &regex).unwrap();
+    assert_eq!(names(&table), ["match", "start", "end", "byte_start", "byte_end", "stem", "group2"]);
+    assert_eq!(table.rows.len(), 2);
+}
+
+#[test]
+fn group_names_may_not_collide_with_result_columns() {
+    let text = TaleaValue::String("abc".to_string());
+    for pattern in ["(?P<match>a)", "(?P<start>a)", "(?P<byte_end>a)"] {
+        let error = matches_table(&text, &compile(pattern, false).unwrap()).unwrap_err();
+        assert!(error.contains("already a column"), "{}: {}", pattern, error);
+    }
+    // `item` only exists when a list is searched.
+    let list = TaleaValue::List(vec![text.clone()]);
+    assert!(matches_table(&text, &compile("(?P<item>a)", false).unwrap()).is_ok());
+    assert!(matches_table(&list, &compile("(?P<item>a)", false).unwrap()).is_err());
+    // A name can also clash with the default name of an unnamed group.
+    assert!(matches_table(&text, &compile("(a)(?P<group1>b)", false).unwrap()).is_err());
+}
+
+#[test]
+fn ranking_keeps_flush_right_columns() {
+    let words = "the cat sat on the mat".split_whitespace().map(str::to_string).collect();
+    let lines = concordance_table(&[("text".to_string(), words)], "the", 20, None).unwrap();
+    let ranked = lines.ranked_by("position", true).unwrap();
+    let flush_right: Vec<&str> = ranked.columns.iter().filter(|column| column.flush_right).map(|column| column.name.as_str()).collect();
+    assert_eq!(flush_right, ["left"]);
+}
+
+#[test]
+fn csv_quotes_cells_with_commas_quotes_and_line_breaks() {
+    assert_eq!(
+        people().to_delimited(',', None),
+        "name,note\n\"Reed, Evelyn\",\"said \"\"hi\"\"\"\nTab\tby,\"two\nlines\"\nPlain,3\n"
+    );
+}
+
+#[test]
+fn tsv_quotes_cells_with_tabs_quotes_and_line_breaks() {
+    assert_eq!(
+        people().to_delimited('\t', None),
+        "name\tnote\nReed, Evelyn\t\"said \"\"hi\"\"\"\n\"Tab\tby\"\t\"two\nlines\"\nPlain\t3\n"
+    );
+}
+
+#[test]
+fn long_cells_are_cut_short_when_printed() {
+    let long = "a".repeat(45);
+    let table = Table::new(vec!["text".to_string(), "left".to_string()], vec![vec![text(&long), text(&format!("{}z", long))]])
+        .unwrap()
+        .flush_right("left");
+    let printed = table.render(None);
+    // 39 characters and an ellipsis; flush-right cells keep their end instead.
+    assert!(printed.contains(&format!("│ {}… │", "a".repeat(39))), "{}", printed);
+    assert!(printed.contains(&format!("│ …{}z │", "a".repeat(38))), "{}", printed);
+    // Line breaks and tabs inside a cell don't break the table's lines.
+    let printed = people().render(None);
+    assert!(printed.contains("│ Tab by       │ two lines │"), "{}", printed);
+}
+
+#[test]
+fn filter_where_keeps_the_rows_whose_condition_holds() {
+    let people = "define people as table of [\n\
+        {name: \"Reed\", age: 34},\n\
+        {name: \"Okafor\", age: 51},\n\
+        {name: \"Lindqvist\", age: 28},\n\
+    ]\n";
+    let table = table_after(&format!("{}filter people where age > 30 as x", people));
+    assert_eq!(table.column("name").unwrap().iter().map(|cell| cell.display(None)).collect::<Vec<_>>(), ["Reed", "Okafor"]);
+    let table = table_after(&format!("{}filter people where age > 30 and name contains \"for\" as x", people));
+    assert_eq!(table.rows.len(), 1);
+    let table = table_after(&format!("{}filter people where age > 100 as x", people));
+    assert_eq!(names(&table), ["name", "age"]);
+    assert!(table.rows.is_empty());
+}
+
+#[test]
+fn selecting_an_unknown_column_names_the_columns_there_are() {
+    let table = people();
+    assert_eq!(table.select(&["nme".to_string()]).unwrap_err(), "The table has no column 'nme'. Did you mean 'name'?");
+    assert_eq!(table.select(&["age".to_string()]).unwrap_err(), "The table has no column 'age'. Its columns are: name, note.");
+    assert_eq!(names(&table.select(&["note".to_string(), "name".to_string()]).unwrap()), ["note", "name"]);
+}